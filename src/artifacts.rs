@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use serde::Deserialize;
+use serde::Serialize;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::config::Profile;
+use crate::config::ProjectKind;
+use crate::storage;
+
+/// Bumped whenever the structure of the manifest changes incompatibly.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Everything a successful build produced, written to `artifacts.json` in the target directory so scripts don't
+/// have to guess at paths.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Artifacts {
+    pub format_version: u32,
+    /// The profile the artifacts were built with.
+    #[serde(default)]
+    pub profile:        Profile,
+    /// The target triple the artifacts were built for.
+    pub target:         String,
+    pub artifacts:      Vec<Artifact>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub name: String,
+    /// Absolute path of the artifact.
+    pub path: PathBuf,
+    pub size: u64,
+    /// Hexadecimal XXH3-64 hash of the artifact's contents.
+    pub hash: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    Bin,
+    StaticLib,
+    SharedLib,
+}
+
+impl ArtifactKind {
+    /// The kind of artifact a project of type `kind` produces.
+    pub fn of(kind: ProjectKind) -> Self {
+        match kind {
+            ProjectKind::Binary => Self::Bin,
+            ProjectKind::StaticLib => Self::StaticLib,
+            ProjectKind::SharedLib => Self::SharedLib,
+        }
+    }
+
+    /// The name the kind is serialized as.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bin => "bin",
+            Self::StaticLib => "staticlib",
+            Self::SharedLib => "sharedlib",
+        }
+    }
+}
+
+impl Artifact {
+    pub fn new(kind: ArtifactKind, name: &str, path: &Path) -> Result<Self, Report> {
+        let contents = fs::read(path)?;
+
+        Ok(Self {
+            kind,
+            name: name.to_owned(),
+            path: path.canonicalize()?,
+            size: contents.len() as u64,
+            hash: format!("{:x}", xxh3_64(&contents)),
+        })
+    }
+}
+
+impl Artifacts {
+    pub fn load(path: &Path) -> Result<Self, Report> {
+        let artifacts: Self = serde_json::from_slice(&fs::read(path)?).map_err(|f| {
+            eyre!(
+                "{} is corrupt ({}); rebuild the project to recreate it",
+                path.display(),
+                f
+            )
+        })?;
+
+        if artifacts.format_version != FORMAT_VERSION {
+            return Err(eyre!(
+                "{} has format version {}, expected {}; rebuild the project",
+                path.display(),
+                artifacts.format_version,
+                FORMAT_VERSION
+            ));
+        }
+
+        Ok(artifacts)
+    }
+
+    /// Writes the manifest to `path` by way of a temporary file, so readers never see a partially written manifest.
+    pub fn write(&self, path: &Path) -> Result<(), Report> {
+        storage::write_atomically(path, &serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn find(&self, kind: ArtifactKind) -> Option<&Artifact> {
+        self.artifacts.iter().find(|f| f.kind == kind)
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::directory::RemoveDirectory;
+use crate::executable::Executable;
+use crate::layout::Layout;
+use crate::status;
+use crate::status::Verb;
+use crate::status::Verbosity;
+use crate::storage;
+
+/// When each object in the object directory was last used by a build, kept in `cache.json` in the state directory so
+/// the least recently used objects can be evicted first.
+///
+/// The file is read and written once per build, so concurrent builds of the same project may lose each other's
+/// updates. That only affects the eviction order, never which objects a build uses.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ObjectCache {
+    /// Seconds since the Unix epoch at which each object, by file name, was last used.
+    last_used: BTreeMap<String, u64>,
+}
+
+impl ObjectCache {
+    pub fn load(layout: &Layout) -> Self {
+        let path = layout.state_directory.join("cache.json");
+
+        match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|_| {
+                status::status(
+                    Verb::Warning,
+                    format!(
+                        "discarding corrupt {}; cache usage will be tracked anew",
+                        path.display()
+                    ),
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Marks the objects a build used as used now, reports how many were reused, and evicts the least recently used
+    /// objects until the object directory is no larger than `max_size` bytes. Objects the build used are never
+    /// evicted.
+    pub fn update(
+        mut self,
+        layout: &Layout,
+        used: &[PathBuf],
+        (hits, misses): (usize, usize),
+        max_size: Option<u64>,
+    ) -> Result<(), Report> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let used = used.iter().map(|f| file_name(f)).collect::<BTreeSet<_>>();
+        for name in &used {
+            self.last_used.insert(name.clone(), now);
+        }
+
+        if !layout.object_directory.is_dir() {
+            return Ok(());
+        }
+
+        let mut objects = Vec::new();
+        for entry in fs::read_dir(&layout.object_directory)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Temporary files belong to compiles that are still running.
+            if metadata.is_file() && !name.contains(".tmp-") {
+                // Dependency files, recorded warnings, and split debug info are used whenever their object is.
+                let owner = [".d", ".warnings", ".dwo"]
+                    .iter()
+                    .find_map(|f| name.strip_suffix(f))
+                    .unwrap_or(&name)
+                    .to_owned();
+                let last_used = self.last_used.get(&owner).copied().unwrap_or_else(|| {
+                    metadata
+                        .modified()
+                        .ok()
+                        .and_then(|f| f.duration_since(UNIX_EPOCH).ok())
+                        .map_or(0, |f| f.as_secs())
+                });
+                objects.push((last_used, name, owner, metadata.len()));
+            }
+        }
+
+        let mut size = objects.iter().map(|f| f.3).sum::<u64>();
+        let mut evicted = 0;
+        if let Some(max_size) = max_size {
+            objects.sort();
+
+            let mut freed = 0;
+            for (_, name, owner, length) in objects {
+                if size <= max_size {
+                    break;
+                }
+                if used.contains(&owner) {
+                    continue;
+                }
+
+                fs::remove_file(layout.object_directory.join(&name))?;
+                self.last_used.remove(&name);
+                size -= length;
+                evicted += 1;
+                freed += length;
+            }
+
+            if evicted > 0 {
+                status::log(
+                    Verbosity::Commands,
+                    Verb::Removed,
+                    format!(
+                        "{} least recently used file{} ({}), since 'cache-max-size' is {}",
+                        evicted,
+                        if evicted == 1 { "" } else { "s" },
+                        human_size(freed),
+                        human_size(max_size)
+                    ),
+                );
+            }
+        }
+
+        status::log(
+            Verbosity::Normal,
+            Verb::Cached,
+            format!(
+                "{} hit{}, {} miss{}, {} evicted, {} in {}",
+                hits,
+                if hits == 1 { "" } else { "s" },
+                misses,
+                if misses == 1 { "" } else { "es" },
+                evicted,
+                human_size(size),
+                layout.object_directory.display()
+            ),
+        );
+
+        // Forget objects that were removed by other means, such as `loki clean --cache`.
+        self.last_used
+            .retain(|name, _| layout.object_directory.join(name).exists());
+
+        fs::create_dir_all(&layout.state_directory)?;
+        storage::write_atomically(
+            &layout.state_directory.join("cache.json"),
+            &serde_json::to_vec_pretty(&self)?,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Removes every cached object along with the record of their use.
+pub fn clean(layout: &Layout) -> Result<(), Report> {
+    RemoveDirectory {
+        directory: layout.object_directory.clone(),
+        within:    layout.project_directory.clone(),
+    }
+    .execute()
+    .map_err(|f| eyre!(f))?;
+
+    match fs::remove_file(layout.state_directory.join("cache.json")) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
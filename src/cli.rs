@@ -0,0 +1,305 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::slice::Iter;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+use crate::compiler::Emit;
+use crate::compiler::Language;
+use crate::config::BuildSettings;
+use crate::config::Color;
+use crate::config::OptimizationLevel;
+use crate::config::Profile;
+use crate::package::ArchiveFormat;
+use crate::status::Verbosity;
+use crate::summary::OutputFormat;
+
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    pub output_format:         OutputFormat,
+    pub settings:              BuildSettings,
+    pub no_probes:             bool,
+    pub verbosity:             Verbosity,
+    pub emit:                  Emit,
+    /// The triple of the target to cross-compile for, or `None` to build for the host.
+    pub target:                Option<String>,
+    pub project_directory:     Option<PathBuf>,
+    pub allow_root_project:    bool,
+    /// Whether to set `max-warnings` in the manifest to the number of warnings the build produced.
+    pub update_warning_budget: bool,
+    /// Whether everything that doesn't depend on a failed step is still built, rather than stopping at the first.
+    pub keep_going:            bool,
+    /// Whether a jobserver in `MAKEFLAGS` is ignored, so concurrency is limited only by `jobs`.
+    pub no_jobserver:          bool,
+}
+
+impl BuildOptions {
+    pub fn parse(args: &[String]) -> Result<Self, Report> {
+        let mut build_options = Self::default();
+        let (mut verbose, mut quiet) = (0, false);
+
+        let mut options = args.iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "--output" =>
+                    build_options.output_format = match value(option, &mut options)? {
+                        "human" => OutputFormat::Human,
+                        "json" => OutputFormat::Json,
+                        other => return Err(eyre!("unknown output format '{}'", other)),
+                    },
+                "-j" | "--jobs" =>
+                    build_options.settings.jobs = Some(
+                        value(option, &mut options)?
+                            .parse()
+                            .map_err(|_| eyre!("'{}' requires a positive number of jobs", option))?,
+                    ),
+                "--launcher" => build_options.settings.launcher = Some(value(option, &mut options)?.to_owned()),
+                "--cc" => build_options.settings.cc = Some(value(option, &mut options)?.to_owned()),
+                "--release" => build_options.settings.profile = Some(Profile::Release),
+                "--debug" => build_options.settings.profile = Some(Profile::Debug),
+                "--target" => build_options.target = Some(value(option, &mut options)?.to_owned()),
+                "--color" =>
+                    build_options.settings.color = Some(match value(option, &mut options)? {
+                        "auto" => Color::Auto,
+                        "always" => Color::Always,
+                        "never" => Color::Never,
+                        other => return Err(eyre!("unknown color setting '{}'", other)),
+                    }),
+                "--max-warnings" =>
+                    build_options.settings.max_warnings = Some(
+                        value(option, &mut options)?
+                            .parse()
+                            .map_err(|_| eyre!("'{}' requires a number of warnings", option))?,
+                    ),
+                "--update-warning-budget" => build_options.update_warning_budget = true,
+                "--stop-on-first-error" => build_options.settings.stop_on_first_error = Some(true),
+                "-k" | "--keep-going" => build_options.keep_going = true,
+                "--no-probes" => build_options.no_probes = true,
+                "--no-jobserver" => build_options.no_jobserver = true,
+                "--verbose" => verbose += 1,
+                flags if flags.len() > 1 && flags.starts_with('-') && flags[1..].bytes().all(|f| f == b'v') =>
+                    verbose += flags.len() - 1,
+                level if level.starts_with("--verbose=") =>
+                    verbose = level["--verbose=".len()..]
+                        .parse()
+                        .map_err(|_| eyre!("'--verbose' requires a level from 0 to 3"))?,
+                "-q" | "--quiet" => quiet = true,
+                "--allow-root-project" => build_options.allow_root_project = true,
+                "--project-dir" => build_options.project_directory = Some(value(option, &mut options)?.into()),
+                emit if emit.starts_with("--emit=") =>
+                    build_options.emit = match &emit["--emit=".len()..] {
+                        "obj" => Emit::Object,
+                        "asm" => Emit::Asm,
+                        other => return Err(eyre!("unknown emit kind '{}'", other)),
+                    },
+                _ => return Err(eyre!("unknown option '{}'", option)),
+            }
+        }
+
+        // Quiet wins regardless of order, so it can be added to a command that already asks for more output.
+        build_options.verbosity = if quiet {
+            Verbosity::Quiet
+        } else {
+            Verbosity::from_count(verbose)
+        };
+
+        Ok(build_options)
+    }
+}
+
+fn value<'a>(option: &str, options: &mut Iter<'a, String>) -> Result<&'a str, Report> {
+    options
+        .next()
+        .map(|f| f.as_str())
+        .ok_or_else(|| eyre!("'{}' requires a value", option))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PackageOptions {
+    pub format: ArchiveFormat,
+    pub build:  BuildOptions,
+}
+
+impl PackageOptions {
+    pub fn parse(args: &[String]) -> Result<Self, Report> {
+        let mut format = ArchiveFormat::default();
+        let mut rest = Vec::new();
+
+        let mut options = args.iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "--format" =>
+                    format = match value(option, &mut options)? {
+                        "tar.gz" => ArchiveFormat::TarGz,
+                        "zip" => ArchiveFormat::Zip,
+                        other => return Err(eyre!("unknown package format '{}'", other)),
+                    },
+                _ => rest.push(option.clone()),
+            }
+        }
+
+        // Packages are meant to be shipped, so they are always built with the release profile.
+        let mut build = BuildOptions::parse(&rest)?;
+        build.settings.profile = Some(Profile::Release);
+
+        Ok(Self {
+            format,
+            build,
+        })
+    }
+}
+
+/// Options for building a project and running its binary.
+pub struct RunOptions {
+    /// Arguments passed to the program, given after `--`.
+    pub arguments: Vec<String>,
+    pub build:     BuildOptions,
+}
+
+impl RunOptions {
+    pub fn parse(args: &[String]) -> Result<Self, Report> {
+        let (rest, arguments) = match args.iter().position(|f| f == "--") {
+            Some(separator) => (&args[..separator], args[separator + 1..].to_vec()),
+            None => (args, Vec::new()),
+        };
+
+        Ok(Self {
+            arguments,
+            build: BuildOptions::parse(rest)?,
+        })
+    }
+}
+
+/// Options for building and running a project's tests.
+pub struct TestOptions {
+    /// The file stem of the only test to run, rather than all of them.
+    pub name:  Option<String>,
+    pub build: BuildOptions,
+}
+
+impl TestOptions {
+    pub fn parse(args: &[String]) -> Result<Self, Report> {
+        let (name, rest) = match args.first() {
+            Some(name) if !name.starts_with('-') => (Some(name.clone()), &args[1..]),
+            _ => (None, args),
+        };
+
+        Ok(Self {
+            name,
+            build: BuildOptions::parse(rest)?,
+        })
+    }
+}
+
+/// Options for creating a project with `new` or `init`.
+#[derive(Default)]
+pub struct NewOptions {
+    /// The package name, which `init` takes from the directory unless given.
+    pub name: Option<String>,
+    /// Whether the source is put next to the manifest rather than in `src`.
+    pub flat: bool,
+}
+
+impl NewOptions {
+    pub fn parse(args: &[String]) -> Result<Self, Report> {
+        let mut new_options = Self::default();
+
+        for option in args {
+            match option.as_str() {
+                "--flat" => new_options.flat = true,
+                name if !name.starts_with('-') && new_options.name.is_none() =>
+                    new_options.name = Some(name.to_owned()),
+                other => return Err(eyre!("unexpected argument '{}'", other)),
+            }
+        }
+
+        Ok(new_options)
+    }
+}
+
+/// Options for building a single source file outside of any project.
+#[derive(Clone, Debug, Default)]
+pub struct SingleFileOptions {
+    pub source:    PathBuf,
+    pub level:     OptimizationLevel,
+    /// Macros given with `--define`, as `NAME` or `NAME=VALUE`.
+    pub defines:   Vec<String>,
+    pub flags:     Vec<String>,
+    pub libraries: Vec<String>,
+    /// Arguments following `--`, passed to the program by `run`.
+    pub arguments: Vec<String>,
+    pub build:     BuildOptions,
+}
+
+impl SingleFileOptions {
+    pub fn parse(source: &str, args: &[String]) -> Result<Self, Report> {
+        let mut single_options = Self {
+            source: source.into(),
+            ..Self::default()
+        };
+        let mut rest = Vec::new();
+
+        let mut options = args.iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "--define" => single_options.defines.push(value(option, &mut options)?.to_owned()),
+                "--cflag" => single_options.flags.push(value(option, &mut options)?.to_owned()),
+                "--lib" => single_options.libraries.push(value(option, &mut options)?.to_owned()),
+                "--" => {
+                    single_options.arguments = options.by_ref().cloned().collect();
+                },
+                level if level.starts_with("-O") =>
+                    single_options.level = level["-O".len()..]
+                        .parse()
+                        .map_err(|_| eyre!("unknown optimization level '{}'", level))?,
+                _ => rest.push(option.clone()),
+            }
+        }
+
+        single_options.build = BuildOptions::parse(&rest)?;
+
+        Ok(single_options)
+    }
+}
+
+/// Returns whether a subcommand's first argument names a source file rather than an option.
+pub fn is_source_file(argument: Option<&String>) -> bool {
+    argument.is_some_and(|f| !f.starts_with('-') && Language::of(Path::new(f)).is_some())
+}
+
+/// Options for `add-file` and `remove-file`.
+#[derive(Clone, Debug, Default)]
+pub struct ScaffoldOptions {
+    pub name:   String,
+    /// Whether `add-file` also creates a header.
+    pub header: bool,
+    /// Whether `add-file` may overwrite existing files.
+    pub force:  bool,
+    /// Whether `remove-file` skips asking for confirmation.
+    pub yes:    bool,
+    pub build:  BuildOptions,
+}
+
+impl ScaffoldOptions {
+    pub fn parse(name: &str, args: &[String]) -> Result<Self, Report> {
+        let mut scaffold_options = Self {
+            name: name.to_owned(),
+            ..Self::default()
+        };
+        let mut rest = Vec::new();
+
+        for option in args {
+            match option.as_str() {
+                "--header" => scaffold_options.header = true,
+                "--force" => scaffold_options.force = true,
+                "-y" | "--yes" => scaffold_options.yes = true,
+                _ => rest.push(option.clone()),
+            }
+        }
+
+        scaffold_options.build = BuildOptions::parse(&rest)?;
+
+        Ok(scaffold_options)
+    }
+}
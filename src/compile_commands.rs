@@ -0,0 +1,49 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::Report;
+use serde::Serialize;
+
+use crate::compiler::CSourceToObject;
+use crate::storage;
+
+/// An entry of a compilation database, the `compile_commands.json` that clangd, clang-tidy and other tools read to
+/// see each source the way the build compiles it.
+#[derive(Clone, Debug, Serialize)]
+pub struct CompileCommand {
+    pub directory: PathBuf,
+    pub file:      PathBuf,
+    pub output:    PathBuf,
+    /// The compiler and its arguments, exactly as a build invokes them when the source is stale.
+    pub arguments: Vec<String>,
+}
+
+impl CompileCommand {
+    /// Describes how `source` is compiled from `directory`. A launcher is left out, as tools expect the compiler
+    /// itself to come first.
+    pub fn new(directory: &Path, source: &CSourceToObject) -> Result<Self, Report> {
+        let compile = CSourceToObject {
+            launcher: None,
+            ..source.clone()
+        };
+        let command = compile.compile_command()?;
+
+        Ok(Self {
+            directory: directory.to_owned(),
+            file:      source.input.clone(),
+            output:    source.output()?,
+            arguments: [command.get_program()]
+                .into_iter()
+                .chain(command.get_args())
+                .map(|f| f.to_string_lossy().into_owned())
+                .collect(),
+        })
+    }
+}
+
+/// Writes the compilation database to `path` by way of a temporary file, so an editor never reads half of it.
+pub fn write(path: &Path, commands: &[CompileCommand]) -> Result<(), Report> {
+    storage::write_atomically(path, &serde_json::to_vec_pretty(commands)?)?;
+
+    Ok(())
+}
@@ -1,94 +1,612 @@
 use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
 use std::fs::File;
+use std::io;
 use std::io::BufReader;
+use std::io::IsTerminal;
 use std::io::Read;
+use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
+use std::process::Output;
+use std::sync::Mutex;
 
-use itertools::Itertools;
 use serde::Deserialize;
 use serde::Serialize;
 use xxhash_rust::xxh3::xxh3_64;
 
+use crate::config::Color;
 use crate::config::Configuration;
+use crate::config::CppStandard;
+use crate::config::Defaults;
 use crate::config::Lto;
 use crate::config::Optimization;
 use crate::config::Standard;
+use crate::depfile;
+use crate::depfile::Change;
+use crate::diagnostic;
+use crate::diagnostic::Diagnostics;
+use crate::diagnostic::LinkDiagnostics;
+use crate::executable::display_command;
+use crate::executable::exit_code;
 use crate::executable::Executable;
+use crate::status;
+use crate::status::Verb;
+use crate::status::Verbosity;
+use crate::storage;
+use crate::summary::BuildSummary;
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct CSourceToObject {
-    pub configuration:    Configuration,
-    pub input:            PathBuf,
-    pub object_directory: PathBuf,
+    pub configuration:        Configuration,
+    /// The optimization of the profile being built, which takes the place of the one in `configuration`.
+    pub optimization:         Optimization,
+    /// Whether debug info is generated.
+    pub debug:                bool,
+    /// Whether split debug info goes into a `.dwo` next to the object, as everywhere but on macOS, where it stays in
+    /// the object for dsymutil to bundle.
+    pub split_dwarf:          bool,
+    pub defaults:             Defaults,
+    pub input:                PathBuf,
+    pub language:             Language,
+    /// The compiler driver, which is the language's own unless another was configured.
+    pub compiler:             String,
+    pub object_directory:     PathBuf,
+    /// Directories searched for `#include "..."` after the source's own directory.
+    pub include_directories:  Vec<PathBuf>,
+    pub launcher:             Option<String>,
+    /// Flags that were requested and accepted by the compiler's feature probes.
+    pub conditional_flags:    Vec<String>,
+    /// Flags given by the user, passed after Loki's own.
+    pub flags:                Vec<String>,
+    /// Anything besides the source and the command line that changes what the compiler generates, such as the
+    /// fingerprint of the compiler.
+    pub extra_inputs:         Vec<String>,
+    pub emit:                 Emit,
+    /// Whether position-independent code is generated, as objects linked into a shared library need.
+    pub position_independent: bool,
+    /// Whether the warnings recorded when up-to-date sources were compiled are counted again.
+    pub replay_warnings:      bool,
+    /// How much of the `jobs` budget the compile takes, as `[weights]` sets for the source. It doesn't change what is
+    /// generated, so it isn't part of the object's name.
+    #[serde(skip)]
+    pub weight:               usize,
+    #[serde(skip)]
+    pub report:               Option<CompileReport>,
 }
 
-impl Executable for CSourceToObject {
-    fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
-        let mut buf = Vec::new();
+/// The language of a source, which decides the compiler driver it is compiled with.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    C,
+    Cpp,
+}
+
+impl Language {
+    /// Returns the language of the source at `path` by its extension, or `None` if it isn't a source.
+    pub fn of(path: &Path) -> Option<Language> {
+        match path.extension()?.to_str()? {
+            "c" => Some(Language::C),
+            "cpp" | "cc" | "cxx" => Some(Language::Cpp),
+            _ => None,
+        }
+    }
+
+    /// The compiler driver for the language. C++ is also linked with its driver, which adds the C++ runtime.
+    pub fn driver(self) -> &'static str {
+        match self {
+            Language::C => "clang",
+            Language::Cpp => "clang++",
+        }
+    }
+}
+
+/// What compiling a source produces.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Emit {
+    #[default]
+    Object,
+    /// Assembly, for inspecting the generated code. Nothing is linked.
+    Asm,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompileReport {
+    pub up_to_date:  bool,
+    pub output:      PathBuf,
+    pub diagnostics: Diagnostics,
+}
+
+/// Asks the compiler for the target triple it generates code for by default.
+pub fn host_triple(compiler: &str) -> io::Result<String> {
+    let output = Command::new(compiler).arg("-dumpmachine").output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "failed to determine the host target triple with '{} -dumpmachine'",
+            compiler
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Warns that `path` is dated in the future, once per build however many sources depend on it.
+fn warn_future(path: &Path) {
+    static WARNED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+    let mut warned = WARNED.lock().unwrap();
+    if !warned.iter().any(|f| f == path) {
+        status::status(
+            Verb::Warning,
+            format!(
+                "{} is dated in the future, so whatever depends on it is rebuilt on every build until the clock \
+                 catches up; check the clock of this machine and of the file server",
+                path.display()
+            ),
+        );
+        warned.push(path.to_owned());
+    }
+}
+
+/// Returns whether a tool failed because the disk filled up, which tools only report in their own messages.
+fn is_storage_full(result: &Output) -> bool {
+    String::from_utf8_lossy(&result.stderr).contains("No space left on device")
+}
+
+/// Moves a tool's output from `temporary` to `path` if the tool succeeded and discards it otherwise, so `path` only
+/// ever holds complete output.
+fn publish(result: &Output, temporary: &Path, path: &Path) -> io::Result<()> {
+    if result.status.success() {
+        return fs::rename(temporary, path).map_err(storage::writing(path));
+    }
+
+    match fs::remove_file(temporary) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => (),
+    }
+
+    match is_storage_full(result) {
+        true => Err(storage::writing(path)(io::ErrorKind::StorageFull.into())),
+        false => Ok(()),
+    }
+}
+
+/// Returns the flag requesting the given diagnostic coloring, if one is needed.
+pub fn color_flag(color: Color) -> Option<&'static str> {
+    match color {
+        Color::Always => Some("-fcolor-diagnostics"),
+        Color::Never => Some("-fno-color-diagnostics"),
+        Color::Auto if io::stderr().is_terminal() => Some("-fcolor-diagnostics"),
+        Color::Auto => None,
+    }
+}
+
+impl CSourceToObject {
+    /// Creates the compiler invocation for this source with every flag that doesn't depend on what is being
+    /// produced, so tools that inspect a source see it exactly as the build does.
+    pub fn command(&self) -> Command {
+        let mut command = match &self.launcher {
+            Some(launcher) => {
+                let mut command = Command::new(launcher);
+                command.arg(&self.compiler);
+                command
+            },
+            None => Command::new(&self.compiler),
+        };
+
+        let standard = match self.language {
+            Language::C => Some(match self.configuration.standard {
+                Standard::C89 => "-std=c89",
+                Standard::C99 => "-std=c99",
+                Standard::C11 => "-std=c11",
+                Standard::C17 => "-std=c17",
+                Standard::C23 => "-std=c2x",
+                Standard::Gnu89 => "-std=gnu89",
+                Standard::Gnu99 => "-std=gnu99",
+                Standard::Gnu11 => "-std=gnu11",
+                Standard::Gnu17 => "-std=gnu17",
+                Standard::Gnu23 => "-std=gnu2x",
+            }),
+            Language::Cpp => self.configuration.cpp_standard.map(|f| match f {
+                CppStandard::Cpp98 => "-std=c++98",
+                CppStandard::Cpp11 => "-std=c++11",
+                CppStandard::Cpp14 => "-std=c++14",
+                CppStandard::Cpp17 => "-std=c++17",
+                CppStandard::Cpp20 => "-std=c++20",
+                CppStandard::Cpp23 => "-std=c++2b",
+                CppStandard::Gnu98 => "-std=gnu++98",
+                CppStandard::Gnu11 => "-std=gnu++11",
+                CppStandard::Gnu14 => "-std=gnu++14",
+                CppStandard::Gnu17 => "-std=gnu++17",
+                CppStandard::Gnu20 => "-std=gnu++20",
+                CppStandard::Gnu23 => "-std=gnu++2b",
+            }),
+        };
+        command.args(standard);
+
+        command.args(self.defaults.flags());
+
+        command.args(&self.conditional_flags);
+
+        for directory in &self.include_directories {
+            command.arg("-iquote").arg(directory);
+        }
+
+        command.args(&self.flags);
+
+        command
+    }
+
+    /// Creates the invocation a build runs when it finds this source stale, writing straight to the object rather than
+    /// to a temporary.
+    pub fn compile_command(&self) -> io::Result<Command> {
+        let mut command = self.command();
+        command.args(self.codegen_args());
+        command.arg("-o").arg(self.output()?);
+        command.arg(&self.input);
+
+        Ok(command)
+    }
+
+    /// Returns the flags that are only passed when generating code.
+    fn codegen_args(&self) -> Vec<&'static str> {
+        let mut args = self.defaults.codegen_flags().to_vec();
+        args.push(self.optimization.level.flag());
+
+        if self.position_independent {
+            args.push("-fPIC");
+        }
+
+        match self.emit {
+            Emit::Object => {
+                match self.optimization.lto {
+                    Some(Lto::Full) => args.push("-flto=full"),
+                    Some(Lto::Thin) => args.push("-flto=thin"),
+                    _ => (),
+                };
+
+                if self.debug || self.configuration.split_debug {
+                    args.push("-g");
+                }
+                // The `.dwo` is named after the output with its extension replaced, so the temporary an object is
+                // compiled to still writes `<object>.dwo`.
+                if self.configuration.split_debug && self.split_dwarf {
+                    args.push("-gsplit-dwarf");
+                }
+
+                args.push("-c");
+            },
+            // LTO would emit compiler IR rather than the machine code the assembly is wanted for.
+            Emit::Asm => args.push("-S"),
+        }
+
+        args
+    }
+
+    /// Returns where the compiled source is written. The name is a hash of the path and contents of the source, the
+    /// compiler, and every argument that can change what the compiler generates or whether it succeeds, so changing the
+    /// configuration recompiles exactly the sources whose command line it changes. Flags that only affect diagnostics,
+    /// such as warnings, are left out, but not the ones that turn diagnostics into failures, like `-Werror`.
+    pub fn output(&self) -> io::Result<PathBuf> {
+        // Sources with the same contents in different directories can still compile differently, since quoted
+        // includes and `__FILE__` depend on where the source is, so they must not share an object.
+        let mut buf = self.input.as_os_str().as_encoded_bytes().to_vec();
+        buf.push(0);
         BufReader::new(File::open(&self.input)?).read_to_end(&mut buf)?;
 
+        let command = self.command();
+        let affects_output = |f: &&OsStr| {
+            let f = f.to_string_lossy();
+            let diagnostic = f.starts_with("-W")
+                && !["-Wl,", "-Wa,", "-Wp,", "-Werror"].iter().any(|g| f.starts_with(g))
+                && f != "-Wfatal-errors";
+            !diagnostic && !f.ends_with("color-diagnostics")
+        };
+        // A launcher like ccache doesn't change the output, so it is left out and the compiler it runs, which is its
+        // first argument, is hashed the same as without it. The language still tells apart a C and a C++ source with
+        // the same contents, and the compiler's version is among the extra inputs while `track-compiler` is set.
+        buf.extend_from_slice(self.compiler.as_bytes());
+        if self.language != Language::C {
+            buf.push(0);
+            buf.extend_from_slice(self.language.driver().as_bytes());
+        }
+        for arg in command
+            .get_args()
+            .skip(usize::from(self.launcher.is_some()))
+            .filter(affects_output)
+            .chain(self.codegen_args().iter().map(OsStr::new))
+            .chain(self.extra_inputs.iter().map(OsStr::new))
+        {
+            buf.push(0);
+            buf.extend_from_slice(arg.as_encoded_bytes());
+        }
+
         let hash = format!("{:x}", xxh3_64(&buf));
-        let output = self.object_directory.join(format!("{}.o", hash));
-
-        let mut command = Command::new("clang");
-
-        command.arg(match self.configuration.standard {
-            Standard::C89 => "-std=c89",
-            Standard::C99 => "-std=c99",
-            Standard::C11 => "-std=c11",
-            Standard::C17 => "-std=c17",
-            Standard::C23 => "-std=c2x",
-            Standard::Gnu89 => "-std=gnu89",
-            Standard::Gnu99 => "-std=gnu99",
-            Standard::Gnu11 => "-std=gnu11",
-            Standard::Gnu17 => "-std=gnu17",
-            Standard::Gnu23 => "-std=gnu2x",
-        });
+        Ok(self.object_directory.join(match self.emit {
+            Emit::Object => format!("{}.o", hash),
+            Emit::Asm => format!("{}.s", hash),
+        }))
+    }
+}
+
+impl Executable for CSourceToObject {
+    fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
+        let output = self.output()?;
 
-        command.arg("-Wall").arg("-Wextra").arg("-Wpedantic");
+        let mut command = self.command();
 
-        if output.exists() {
-            command.arg("-fsyntax-only");
-        } else {
-            command.arg("-fwrapv");
+        // An empty or unreadable object is left over from a crash or a full disk, not a valid object, so the source is
+        // compiled again to replace it. Since the object is named after the source, only the headers it includes can
+        // make an existing object stale, and the dependency file written alongside it records which those are. Their
+        // timestamps can only be compared while none is ahead of the clock, so anything dated in the future, including
+        // the object, is stale.
+        let depfile = depfile::path(&output);
+        let stale = match File::open(&output).and_then(|f| f.metadata()) {
+            Ok(object) if object.len() == 0 => Some(format!("{} is empty", output.display())),
+            Ok(object) if depfile::in_the_future(object.modified()?) => {
+                warn_future(&output);
+                Some(format!("{} is dated in the future", output.display()))
+            },
+            Ok(object) => match depfile::newer_prerequisite(&depfile, object.modified()?) {
+                Ok(None) => None,
+                Ok(Some(Change::Modified(prerequisite))) => Some(format!(
+                    "{} changed since {} was compiled",
+                    prerequisite.display(),
+                    output.display()
+                )),
+                Ok(Some(Change::Future(prerequisite))) => {
+                    warn_future(&prerequisite);
+                    Some(format!("{} is dated in the future", prerequisite.display()))
+                },
+                Err(_) => Some(format!("{} is missing or corrupt", depfile.display())),
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound =>
+                Some(format!("{} does not exist", output.display())),
+            Err(error) => Some(format!("{} is unreadable ({})", output.display(), error)),
+        };
+        // An object compiled before its warnings were recorded has none to replay, so its source is compiled again.
+        let warnings = diagnostic::warnings_path(&output);
+        let recorded = self.replay_warnings.then(|| diagnostic::recorded_warnings(&warnings));
+        let stale = match recorded {
+            Some(None) if stale.is_none() => Some(format!("{} is missing or corrupt", warnings.display())),
+            _ => stale,
+        };
+        let up_to_date = stale.is_none();
+        let temporary = storage::temporary_path(&output);
+        let temporary_depfile = depfile::path(&temporary);
+
+        status::log(Verbosity::Decisions, Verb::Checking, match &stale {
+            None => format!(
+                "{}: up to date, {} is newer than everything it depends on",
+                self.input.display(),
+                output.display()
+            ),
+            Some(reason) => format!("{}: stale, {}", self.input.display(), reason),
+        });
 
-            match self.configuration.optimization.lto {
-                Some(Lto::Full) => _ = command.arg("-flto=full"),
-                Some(Lto::Thin) => _ = command.arg("-flto=thin"),
-                _ => (),
-            };
+        if up_to_date {
+            status::step(Verb::Fresh, self.input.display());
+            self.report = Some(CompileReport {
+                up_to_date,
+                output,
+                diagnostics: Diagnostics {
+                    warnings: recorded.flatten().unwrap_or(0),
+                    errors:   0,
+                },
+            });
 
-            command.arg("-c").arg("-o").arg(&output);
+            return Ok(0);
         }
 
+        command.args(self.codegen_args());
+        command.arg("-MMD").arg("-MF").arg(&temporary_depfile);
+        command.arg("-o").arg(&temporary);
         command.arg(&self.input);
 
-        println!(
-            "{}",
-            [&[command.get_program()], &command.get_args().collect_vec()[..]]
-                .concat()
-                .iter()
-                .map(|f| f.to_str().unwrap())
-                .join(" ")
-        );
+        status::step(Verb::Compiling, self.input.display());
+        status::log(Verbosity::Commands, Verb::Running, display_command(&command));
+
+        let result = command.output()?;
+        io::stderr().write_all(&result.stderr)?;
+
+        let diagnostics = Diagnostics::parse(&String::from_utf8_lossy(&result.stderr));
+        publish(&result, &temporary_depfile, &depfile)?;
+        publish(&result, &temporary, &output)?;
+        if result.status.success() {
+            storage::write_atomically(&warnings, diagnostics.warnings.to_string().as_bytes())
+                .map_err(storage::writing(&warnings))?;
+        }
+
+        // After the diagnostics, so the command that produced them is easy to find and rerun.
+        if let Some(code) = result.status.code().filter(|f| *f != 0) {
+            status::status(
+                Verb::Failed,
+                format!(
+                    "compiling {} ({} exited with status {})",
+                    self.input.display(),
+                    self.compiler,
+                    code
+                ),
+            );
+            eprintln!("{:>12} {}", "", display_command(&command));
+        }
+
+        if diagnostics.warnings > 0 {
+            status::status(
+                Verb::Warning,
+                format!(
+                    "{} generated {} warning{}",
+                    self.input.display(),
+                    diagnostics.warnings,
+                    if diagnostics.warnings == 1 { "" } else { "s" }
+                ),
+            );
+        }
+
+        self.report = Some(CompileReport {
+            up_to_date,
+            output,
+            diagnostics,
+        });
+
+        exit_code(&command, result.status)
+    }
+
+    fn describe(&self) -> String {
+        format!("compile {}", self.input.display())
+    }
+
+    fn is_step(&self) -> bool {
+        true
+    }
+
+    fn weight(&self) -> usize {
+        self.weight
+    }
+
+    fn summarize(&self, code: i32, summary: &mut BuildSummary) {
+        if code != 0 {
+            summary.fail(&self.describe(), &self.compiler, code);
+        }
+
+        if let Some(report) = &self.report {
+            if self.emit == Emit::Asm && code == 0 {
+                summary.emitted.push(report.output.clone());
+            }
+
+            if report.up_to_date {
+                summary.skipped += 1;
+            } else {
+                summary.compiled += 1;
+            }
 
-        command.execute()
+            summary.warnings += report.diagnostics.warnings;
+            if report.diagnostics.warnings > 0 {
+                summary
+                    .warning_sources
+                    .push((self.input.clone(), report.diagnostics.warnings));
+            }
+            summary.errors += report.diagnostics.errors;
+        }
     }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct LinkObjectsToBinary {
+    /// The driver that links, which is the C++ one if any of the objects is C++.
+    pub compiler:     String,
+    /// The linker the driver is told to use with `-fuse-ld`, or `None` for the driver's default.
+    pub linker:       Option<String>,
     pub optimization: Optimization,
     pub inputs:       Vec<PathBuf>,
+    /// Flags given by the user, passed after the inputs.
+    pub flags:        Vec<String>,
+    /// Directories searched for libraries, passed as `-L` before any library.
+    pub library_dirs: Vec<PathBuf>,
+    /// Linker flags that pkg-config gave for the project's packages, passed before `libraries`.
+    pub package_libs: Vec<String>,
+    /// Libraries to link against, by the name given to `-l`, passed last and in order since static linking needs
+    /// each library after everything that uses it.
+    pub libraries:    Vec<String>,
+    /// Whether a shared library is linked rather than an executable.
+    pub shared:       bool,
     pub output:       PathBuf,
+    /// Where the inputs of the last link are recorded, so that linking is skipped while they are unchanged.
+    pub record:       Option<PathBuf>,
+    #[serde(skip)]
+    pub relinked:     bool,
+}
+
+/// Returns why `output` has to be made again from `inputs`, or `None` if it is newer than every input and `record`
+/// shows it was made with the same `key`, which identifies everything besides the contents of the inputs.
+fn stale_output(output: &Path, inputs: &[PathBuf], record: &Path, key: &str) -> io::Result<Option<String>> {
+    let modified = match fs::metadata(output) {
+        Ok(output) => output.modified()?,
+        Err(_) => return Ok(Some(format!("{} does not exist", output.display()))),
+    };
+
+    if fs::read_to_string(record).ok().as_deref() != Some(key) {
+        return Ok(Some("the objects or link options changed".to_owned()));
+    }
+
+    for input in inputs {
+        match fs::metadata(input).and_then(|f| f.modified()) {
+            Ok(time) if time <= modified => (),
+            _ => return Ok(Some(format!("{} changed since it was linked", input.display()))),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks `output` against `record` with [`stale_output`], logging the decision. Returns whether it is up to date.
+fn up_to_date(output: &Path, inputs: &[PathBuf], record: &Path, key: &str) -> io::Result<bool> {
+    match stale_output(output, inputs, record, key)? {
+        None => {
+            status::log(
+                Verbosity::Decisions,
+                Verb::Checking,
+                format!("{}: up to date, newer than every object", output.display()),
+            );
+            status::step(Verb::Fresh, output.display());
+            Ok(true)
+        },
+        Some(reason) => {
+            status::log(
+                Verbosity::Decisions,
+                Verb::Checking,
+                format!("{}: stale, {}", output.display(), reason),
+            );
+            Ok(false)
+        },
+    }
+}
+
+/// Records `key` once `output` was made, so the next build can tell it is up to date.
+fn record_output(record: &Path, key: &str) -> io::Result<()> {
+    fs::create_dir_all(record.parent().unwrap())?;
+    storage::write_atomically(record, key.as_bytes())
+}
+
+impl LinkObjectsToBinary {
+    /// Identifies everything besides the contents of the objects that the binary depends on.
+    fn inputs_key(&self) -> String {
+        format!(
+            "{}\0{:?}\0{:?}\0{:?}\0{:?}\0{:?}\0{:?}\0{:?}",
+            self.compiler,
+            self.linker,
+            self.optimization,
+            self.inputs,
+            self.flags,
+            self.library_dirs,
+            self.package_libs,
+            self.libraries
+        )
+    }
 }
 
 impl Executable for LinkObjectsToBinary {
     fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
-        let mut command = Command::new("clang");
+        if let Some(record) = &self.record {
+            if up_to_date(&self.output, &self.inputs, record, &self.inputs_key())? {
+                return Ok(0);
+            }
+        }
 
-        command.arg("-fuse-ld=lld");
+        let mut command = Command::new(&self.compiler);
+
+        if let Some(linker) = &self.linker {
+            command.arg(format!("-fuse-ld={}", linker));
+        }
+        if self.shared {
+            command.arg("-shared");
+        }
+        command.arg(self.optimization.level.flag());
 
         match self.optimization.lto {
             Some(Lto::Full) => _ = command.arg("-flto=full"),
@@ -96,19 +614,179 @@ impl Executable for LinkObjectsToBinary {
             _ => (),
         };
 
+        let temporary = storage::temporary_path(&self.output);
+
         command.arg("-o");
-        command.arg(&self.output);
+        command.arg(&temporary);
         command.args(&self.inputs);
+        command.args(&self.flags);
 
-        println!(
-            "{}",
-            [&[command.get_program()], &command.get_args().collect_vec()[..]]
-                .concat()
-                .iter()
-                .map(|f| f.to_str().unwrap())
-                .join(" ")
-        );
+        for directory in &self.library_dirs {
+            command.arg(format!("-L{}", directory.display()));
+        }
+        command.args(&self.package_libs);
+        for library in &self.libraries {
+            command.arg(format!("-l{}", library));
+        }
+
+        status::step(Verb::Linking, self.output.display());
+        status::log(Verbosity::Commands, Verb::Running, display_command(&command));
+
+        let result = command.output()?;
+        io::stdout().write_all(&result.stdout)?;
+
+        publish(&result, &temporary, &self.output)?;
+        self.relinked = result.status.success();
+
+        if let Some(record) = self.record.as_ref().filter(|_| self.relinked) {
+            record_output(record, &self.inputs_key())?;
+        }
+
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let diagnostics = LinkDiagnostics::parse(&stderr);
+
+        if status::verbosity() >= Verbosity::Commands || result.status.success() || diagnostics.is_empty() {
+            io::stderr().write_all(&result.stderr)?;
+        }
 
-        command.execute()
+        if !result.status.success() && !diagnostics.is_empty() {
+            status::status(Verb::Failed, format!("linking {}", self.output.display()));
+            eprint!("{}", diagnostics.summary());
+        }
+
+        exit_code(&command, result.status)
+    }
+
+    fn describe(&self) -> String {
+        format!("link {}", self.output.display())
+    }
+
+    fn is_step(&self) -> bool {
+        true
+    }
+
+    fn summarize(&self, code: i32, summary: &mut BuildSummary) {
+        if code != 0 {
+            summary.fail(&self.describe(), &self.compiler, code);
+        }
+        if self.relinked {
+            summary.relinked += 1;
+        }
+    }
+}
+
+/// Archives objects into a static library with `ar`, or the archiver of the target.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ArchiveObjectsToStaticLib {
+    /// The archiver, which is `ar` unless cross-compiling.
+    pub archiver: String,
+    pub inputs:   Vec<PathBuf>,
+    pub output:   PathBuf,
+    /// Where the inputs of the last archive are recorded, so that archiving is skipped while they are unchanged.
+    pub record:   Option<PathBuf>,
+    #[serde(skip)]
+    pub archived: bool,
+}
+
+impl ArchiveObjectsToStaticLib {
+    fn inputs_key(&self) -> String {
+        format!("{}\0{:?}", self.archiver, self.inputs)
+    }
+}
+
+impl Executable for ArchiveObjectsToStaticLib {
+    fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
+        if let Some(record) = &self.record {
+            if up_to_date(&self.output, &self.inputs, record, &self.inputs_key())? {
+                return Ok(0);
+            }
+        }
+
+        // `ar` adds to an existing archive, so it always starts from a fresh temporary. `D` zeroes the timestamps,
+        // owners and modes of the members, and sorting them fixes their order, so the same objects always make
+        // the same bytes.
+        let temporary = storage::temporary_path(&self.output);
+        let mut inputs = self.inputs.clone();
+        inputs.sort();
+        let mut command = Command::new(&self.archiver);
+        command.arg("rcsD").arg(&temporary).args(&inputs);
+
+        status::step(Verb::Archiving, self.output.display());
+        status::log(Verbosity::Commands, Verb::Running, display_command(&command));
+
+        let result = command.output()?;
+        io::stderr().write_all(&result.stderr)?;
+
+        publish(&result, &temporary, &self.output)?;
+        self.archived = result.status.success();
+
+        if let Some(record) = self.record.as_ref().filter(|_| self.archived) {
+            record_output(record, &self.inputs_key())?;
+        }
+
+        exit_code(&command, result.status)
+    }
+
+    fn describe(&self) -> String {
+        format!("archive {}", self.output.display())
+    }
+
+    fn is_step(&self) -> bool {
+        true
+    }
+
+    fn summarize(&self, code: i32, summary: &mut BuildSummary) {
+        if code != 0 {
+            summary.fail(&self.describe(), &self.archiver, code);
+        }
+        if self.archived {
+            summary.relinked += 1;
+        }
+    }
+}
+
+/// Collects the debug info of a linked binary into `<binary>.dSYM` with `dsymutil`.
+#[derive(Clone, Debug)]
+pub struct BundleDebugInfo {
+    pub binary: PathBuf,
+}
+
+impl BundleDebugInfo {
+    pub fn output(&self) -> PathBuf {
+        let mut name = self.binary.file_name().unwrap_or_default().to_owned();
+        name.push(".dSYM");
+        self.binary.with_file_name(name)
+    }
+}
+
+impl Executable for BundleDebugInfo {
+    fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
+        let mut command = Command::new("dsymutil");
+        command.arg(&self.binary).arg("-o").arg(self.output());
+
+        status::log(Verbosity::Commands, Verb::Running, display_command(&command));
+
+        let result = command.output()?;
+        io::stderr().write_all(&result.stderr)?;
+
+        if result.status.success() {
+            status::step(Verb::Generated, self.output().display());
+        }
+
+        exit_code(&command, result.status)
+    }
+
+    fn describe(&self) -> String {
+        format!("bundle debug info of {}", self.binary.display())
+    }
+
+    fn is_step(&self) -> bool {
+        true
+    }
+
+    fn summarize(&self, code: i32, summary: &mut BuildSummary) {
+        if code != 0 {
+            summary.fail(&self.describe(), "dsymutil", code);
+        }
     }
 }
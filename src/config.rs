@@ -1,6 +1,15 @@
+use std::collections::BTreeMap;
+use std::env;
 use std::fmt;
+use std::fmt::Display;
 use std::fmt::Formatter;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread::available_parallelism;
 
+use glob::Pattern;
 use serde::de::Visitor;
 use serde::Deserialize;
 use serde::Serialize;
@@ -9,31 +18,246 @@ use serde::Serialize;
 pub struct Project {
     pub package:       Package,
     pub configuration: Configuration,
+    #[serde(default)]
+    pub build:         BuildSettings,
+    #[serde(rename = "config-header")]
+    pub config_header: Option<ConfigHeader>,
+    /// Overrides of the `debug` and `release` profiles.
+    #[serde(default)]
+    pub profile:       Profiles,
+    #[serde(default)]
+    pub dependencies:  Dependencies,
+    /// Toolchains for cross-compiling, by target triple, used when building with `--target`.
+    #[serde(default)]
+    pub target:        BTreeMap<String, TargetSettings>,
+    #[serde(default)]
+    pub weights:       Weights,
 }
 
 impl Default for Project {
     fn default() -> Self {
         Self {
             package:       Package {
-                name: "my_project".to_owned(),
-                kind: ProjectKind::Binary,
+                name:             "my_project".to_owned(),
+                kind:             ProjectKind::Binary,
+                version:          None,
+                include:          Vec::new(),
+                defaults:         Defaults::default(),
+                source_dir:       None,
+                source_dirs:      None,
+                sources:          None,
+                discover_sources: false,
+                watch_ignore:     Vec::new(),
             },
             configuration: Default::default(),
+            build:         Default::default(),
+            config_header: None,
+            profile:       Default::default(),
+            dependencies:  Default::default(),
+            target:        Default::default(),
+            weights:       Default::default(),
         }
     }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Package {
-    pub name: String,
-    #[serde(rename = "type")]
-    pub kind: ProjectKind,
+    pub name:             String,
+    /// What the project builds, a binary unless set.
+    #[serde(default, rename = "type")]
+    pub kind:             ProjectKind,
+    pub version:          Option<String>,
+    /// Glob patterns, relative to the project directory, of extra files to add to packages.
+    #[serde(default)]
+    pub include:          Vec<String>,
+    /// The set of implicit defaults the project was written against.
+    #[serde(default)]
+    pub defaults:         Defaults,
+    /// Directory, relative to the project directory, that sources are discovered in instead of `src`.
+    #[serde(rename = "source-dir")]
+    pub source_dir:       Option<PathBuf>,
+    /// Several directories that sources are discovered in, for projects that split them up.
+    #[serde(rename = "source-dirs")]
+    pub source_dirs:      Option<Vec<PathBuf>>,
+    /// The exact sources to build, relative to the project directory. When set, the source directories are not
+    /// searched unless `discover-sources` is also set.
+    pub sources:          Option<Vec<PathBuf>>,
+    /// Also discovers sources in the source directories when `sources` is set.
+    #[serde(default, rename = "discover-sources")]
+    pub discover_sources: bool,
+    /// Glob patterns, relative to the project directory, of files and directories `loki watch` doesn't watch.
+    #[serde(default, rename = "watch-ignore")]
+    pub watch_ignore:     Vec<String>,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+/// How much of the `jobs` budget compiling a source takes, by glob patterns of sources relative to the project
+/// directory, as written under `[weights]`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(try_from = "BTreeMap<String, NonZeroUsize>", into = "BTreeMap<String, NonZeroUsize>")]
+pub struct Weights {
+    patterns: Vec<(Pattern, NonZeroUsize)>,
+}
+
+impl Weights {
+    /// The weight of compiling the source at `relative`: the largest of the patterns it matches, or 1 if it matches
+    /// none.
+    pub fn of(&self, relative: &Path) -> usize {
+        self.patterns
+            .iter()
+            .filter(|(pattern, _)| pattern.matches_path(relative))
+            .map(|(_, weight)| weight.get())
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+impl TryFrom<BTreeMap<String, NonZeroUsize>> for Weights {
+    type Error = String;
+
+    fn try_from(weights: BTreeMap<String, NonZeroUsize>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            patterns: weights
+                .into_iter()
+                .map(|(pattern, weight)| {
+                    Pattern::new(&pattern)
+                        .map(|f| (f, weight))
+                        .map_err(|error| format!("invalid pattern '{}' in 'weights': {}", pattern, error))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<Weights> for BTreeMap<String, NonZeroUsize> {
+    fn from(weights: Weights) -> Self {
+        weights
+            .patterns
+            .into_iter()
+            .map(|(pattern, weight)| (pattern.as_str().to_owned(), weight))
+            .collect()
+    }
+}
+
+/// The libraries a project links against, as written under `[dependencies]`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependencies {
+    /// Libraries linked by the name given to `-l`, such as `m` or `pthread`.
+    #[serde(default)]
+    pub libs:       Vec<String>,
+    /// Directories, relative to the project, searched for libraries with `-L`.
+    #[serde(default, rename = "lib-dirs")]
+    pub lib_dirs:   Vec<PathBuf>,
+    /// Packages whose compiler and linker flags are looked up with pkg-config.
+    #[serde(default, rename = "pkg-config")]
+    pub pkg_config: Vec<String>,
+}
+
+/// The toolchain for building for another target than the host, as written under `[target.<triple>]`. Tools that
+/// aren't set are the GNU ones prefixed with the triple, such as `<triple>-gcc`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetSettings {
+    /// Compiler for C sources, and for linking when there are no C++ sources.
+    pub cc:      Option<String>,
+    /// Compiler for C++ sources, and for linking when there are any.
+    pub cxx:     Option<String>,
+    /// Archiver for static libraries.
+    pub ar:      Option<String>,
+    /// Linker the compiler is told to link with through `-fuse-ld`, such as `lld`, rather than its own default.
+    pub linker:  Option<String>,
+    /// Directory, relative to the project, holding the target's headers and libraries, passed as `--sysroot`.
+    pub sysroot: Option<PathBuf>,
+    /// Flags passed to every compiler invocation after those of `[build]`.
+    #[serde(default)]
+    pub cflags:  Vec<String>,
+    /// Flags passed when linking after those of `[build]`.
+    #[serde(default)]
+    pub ldflags: Vec<String>,
+}
+
+/// A header of preprocessor definitions that is generated before anything is compiled, like the `config.h` of
+/// Autoconf. Sources include it as `#include "<name>"`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigHeader {
+    #[serde(default = "ConfigHeader::default_name")]
+    pub name:    String,
+    /// Macros with fixed values.
+    #[serde(default)]
+    pub defines: BTreeMap<String, Define>,
+    /// Macros that are defined to 1 when a feature check succeeds, and left undefined otherwise.
+    #[serde(default)]
+    pub checks:  BTreeMap<String, Check>,
+}
+
+impl ConfigHeader {
+    fn default_name() -> String {
+        "config.h".to_owned()
+    }
+}
+
+/// The value of a macro in a generated header.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Define {
+    /// `true` defines the macro to 1, `false` leaves it undefined.
+    Flag(bool),
+    Integer(i64),
+    /// Defined as a C string literal.
+    Text(String),
+}
+
+/// A feature of the toolchain or system that a generated header can record.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Check {
+    /// Whether `#include <header>` compiles.
+    Header(String),
+    /// Whether a program calling the function links.
+    Function(String),
+}
+
+/// A versioned set of the flags Loki adds on its own. New sets may be introduced as Loki's defaults evolve, while
+/// existing projects keep building exactly as they did with the set they pin.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub enum Defaults {
+    /// `-Wall -Wextra -Wpedantic`, and `-fwrapv` for code generation.
+    #[default]
+    #[serde(rename = "2023")]
+    Y2023,
+    /// The 2023 set, with `-Wshadow` and `-Wformat=2` for shadowed variables and unchecked format strings.
+    #[serde(rename = "2025")]
+    Y2025,
+}
+
+impl Defaults {
+    /// Flags passed to every compiler invocation.
+    pub fn flags(self) -> &'static [&'static str] {
+        match self {
+            Defaults::Y2023 => &["-Wall", "-Wextra", "-Wpedantic"],
+            Defaults::Y2025 => &["-Wall", "-Wextra", "-Wpedantic", "-Wshadow", "-Wformat=2"],
+        }
+    }
+
+    /// Flags passed only when generating code.
+    pub fn codegen_flags(self) -> &'static [&'static str] {
+        match self {
+            Defaults::Y2023 | Defaults::Y2025 => &["-fwrapv"],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ProjectKind {
+    #[default]
+    #[serde(alias = "bin")]
     Binary,
+    /// An archive of the objects, `lib<name>.a`, made with `ar`.
+    StaticLib,
+    /// A library linked from position-independent objects, `lib<name>.so`, or `lib<name>.dylib` on macOS.
+    SharedLib,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
@@ -41,8 +265,117 @@ pub enum ProjectKind {
 pub struct Configuration {
     #[serde(rename = "c-standard")]
     pub standard:     Standard,
+    /// The standard C++ sources are compiled against, or the compiler's own default if unset.
+    #[serde(rename = "cpp-standard")]
+    pub cpp_standard: Option<CppStandard>,
     #[serde(flatten)]
     pub optimization: Optimization,
+    /// Optimization level used when linking, if it differs from the one used when compiling.
+    #[serde(rename = "link-opt-level")]
+    pub link_level:   Option<OptimizationLevel>,
+    /// Whether debug info is generated and kept out of the binary: in `.dwo` files next to the objects, or on macOS
+    /// in a `.dSYM` bundle next to the binary.
+    #[serde(default, rename = "split-debug")]
+    pub split_debug:  bool,
+}
+
+/// Which of the project's build profiles is built, each into its own directory below `target`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Unoptimized with debug info, for development.
+    #[default]
+    Debug,
+    /// Optimized as `[configuration]` says, for shipping.
+    Release,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profiles {
+    #[serde(default)]
+    pub debug:   ProfileSettings,
+    #[serde(default)]
+    pub release: ProfileSettings,
+}
+
+/// The overrides of a profile, as written under `[profile.<name>]`. Anything unset keeps the profile's default.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileSettings {
+    #[serde(rename = "opt-level")]
+    pub level:   Option<OptimizationLevel>,
+    pub lto:     Option<Lto>,
+    /// Whether debug info is generated with `-g`.
+    pub debug:   Option<bool>,
+    /// Flags passed to the compiler after the `cflags` of `[build]`.
+    pub cflags:  Option<Vec<String>>,
+    /// Flags passed to the linker after the `ldflags` of `[build]`.
+    pub ldflags: Option<Vec<String>>,
+}
+
+/// A profile with its overrides applied to its defaults, ready to build with.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BuildProfile {
+    pub profile:      Profile,
+    pub optimization: Optimization,
+    /// Optimization level used when linking, which LTO shares with compilation since the objects depend on it.
+    pub link_level:   OptimizationLevel,
+    pub debug:        bool,
+    pub cflags:       Vec<String>,
+    pub ldflags:      Vec<String>,
+}
+
+impl BuildProfile {
+    /// The optimization to link with.
+    pub fn link_optimization(&self) -> Optimization {
+        Optimization {
+            level: self.link_level,
+            ..self.optimization
+        }
+    }
+}
+
+impl Project {
+    /// Resolves `profile`. The debug profile defaults to `-O0 -g`, and the release profile to the optimization of
+    /// `[configuration]` without debug info, so a project written before profiles existed builds as it did with
+    /// `--release`.
+    pub fn profile(&self, profile: Profile) -> BuildProfile {
+        let (overrides, optimization, link_level, debug) = match profile {
+            Profile::Debug => (&self.profile.debug, Optimization::default(), None, true),
+            Profile::Release => (
+                &self.profile.release,
+                self.configuration.optimization,
+                self.configuration.link_level,
+                false,
+            ),
+        };
+
+        let optimization = Optimization {
+            level: overrides.level.unwrap_or(optimization.level),
+            lto:   overrides.lto.or(optimization.lto),
+        };
+
+        BuildProfile {
+            profile,
+            optimization,
+            link_level: link_level
+                .filter(|_| overrides.level.is_none())
+                .unwrap_or(optimization.level),
+            debug: overrides.debug.unwrap_or(debug),
+            cflags: overrides.cflags.clone().unwrap_or_default(),
+            ldflags: overrides.ldflags.clone().unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
@@ -61,6 +394,34 @@ pub enum Standard {
     Gnu23,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum CppStandard {
+    #[serde(rename = "c++98")]
+    Cpp98,
+    #[serde(rename = "c++11")]
+    Cpp11,
+    #[serde(rename = "c++14")]
+    Cpp14,
+    #[serde(rename = "c++17")]
+    Cpp17,
+    #[serde(rename = "c++20")]
+    Cpp20,
+    #[serde(rename = "c++23")]
+    Cpp23,
+    #[serde(rename = "gnu++98")]
+    Gnu98,
+    #[serde(rename = "gnu++11")]
+    Gnu11,
+    #[serde(rename = "gnu++14")]
+    Gnu14,
+    #[serde(rename = "gnu++17")]
+    Gnu17,
+    #[serde(rename = "gnu++20")]
+    Gnu20,
+    #[serde(rename = "gnu++23")]
+    Gnu23,
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
 pub struct Optimization {
     #[serde(rename = "opt-level")]
@@ -81,6 +442,40 @@ pub enum OptimizationLevel {
     Ofast,
 }
 
+impl OptimizationLevel {
+    pub fn flag(self) -> &'static str {
+        match self {
+            OptimizationLevel::O0 => "-O0",
+            OptimizationLevel::O1 => "-O1",
+            OptimizationLevel::O2 => "-O2",
+            OptimizationLevel::O3 => "-O3",
+            OptimizationLevel::Og => "-Og",
+            OptimizationLevel::Os => "-Os",
+            OptimizationLevel::Oz => "-Oz",
+            OptimizationLevel::Ofast => "-Ofast",
+        }
+    }
+}
+
+impl FromStr for OptimizationLevel {
+    type Err = ();
+
+    /// Parses a level as written after `-O`.
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        Ok(match level {
+            "0" => OptimizationLevel::O0,
+            "1" | "" => OptimizationLevel::O1,
+            "2" => OptimizationLevel::O2,
+            "3" => OptimizationLevel::O3,
+            "g" => OptimizationLevel::Og,
+            "s" => OptimizationLevel::Os,
+            "z" => OptimizationLevel::Oz,
+            "fast" => OptimizationLevel::Ofast,
+            _ => return Err(()),
+        })
+    }
+}
+
 impl Serialize for OptimizationLevel {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -152,3 +547,361 @@ pub enum Lto {
     Full,
     Thin,
 }
+
+/// Settings that can be given on the command line, in the environment, in the project manifest's `[build]` table, or in
+/// the user-wide configuration file. Every layer uses this same shape; see [`resolve_settings`] for how they combine.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildSettings {
+    /// Maximum number of concurrently running jobs.
+    pub jobs:                Option<NonZeroUsize>,
+    /// The profile built, which `--release` and `--debug` choose on the command line.
+    pub profile:             Option<Profile>,
+    /// Program the compiler invocation is prefixed with, such as `ccache`.
+    pub launcher:            Option<String>,
+    /// Whether compiler diagnostics are colored.
+    pub color:               Option<Color>,
+    /// Maximum number of files the source walk may visit before Loki assumes the project root is wrong.
+    #[serde(rename = "max-source-files")]
+    pub max_source_files:    Option<usize>,
+    /// Number of warnings the whole project may produce before the build fails.
+    #[serde(rename = "max-warnings")]
+    pub max_warnings:        Option<usize>,
+    /// Whether each source stops compiling at its first error.
+    #[serde(rename = "stop-on-first-error")]
+    pub stop_on_first_error: Option<bool>,
+    /// Size in bytes the object cache is kept under by evicting the least recently used objects.
+    #[serde(rename = "cache-max-size")]
+    pub cache_max_size:      Option<u64>,
+    /// Whether objects are rebuilt when the compiler changes.
+    #[serde(rename = "track-compiler")]
+    pub track_compiler:      Option<bool>,
+    /// Compiler run in place of `clang` for C sources, and for linking when there are no C++ sources.
+    pub cc:                  Option<String>,
+    /// Extra flags passed to every compiler invocation after Loki's own.
+    pub cflags:              Option<Vec<String>>,
+    /// Extra flags passed when linking, before the libraries.
+    pub ldflags:             Option<Vec<String>>,
+    /// Directories, relative to the project directory, searched for every kind of `#include` with `-I`.
+    #[serde(rename = "include-dirs")]
+    pub include_dirs:        Option<Vec<PathBuf>>,
+}
+
+impl BuildSettings {
+    /// Loads the user-wide configuration file, returning empty settings when it doesn't exist.
+    pub fn load_user() -> Result<Self, color_eyre::Report> {
+        match user_config_path() {
+            Some(path) if path.exists() => Ok(toml::from_str(&std::fs::read_to_string(&path)?)?),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the settings conventionally given as environment variables, which is only `CC` for the compiler.
+    pub fn from_environment() -> Self {
+        Self {
+            cc: env::var("CC").ok().filter(|f| !f.is_empty()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Location of the user-wide configuration file: `$XDG_CONFIG_HOME/loki/config.toml`, falling back to
+/// `~/.config/loki/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|f| !f.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|config| config.join("loki/config.toml"))
+}
+
+/// Location of Loki's user-wide cache: `$XDG_CACHE_HOME/loki`, falling back to `~/.cache/loki`.
+pub fn user_cache_directory() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|f| !f.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|cache| cache.join("loki"))
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The layer an effective setting was taken from, in order of decreasing precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    CommandLine,
+    Environment,
+    Manifest,
+    UserConfig,
+    /// Derived from the triple given with `--target`.
+    Target,
+    Default,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match self {
+            Origin::CommandLine => "command line",
+            Origin::Environment => "environment",
+            Origin::Manifest => "loki.toml",
+            Origin::UserConfig => "user config",
+            Origin::Target => "target",
+            Origin::Default => "default",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Setting<T> {
+    pub value:  T,
+    pub origin: Origin,
+}
+
+#[derive(Clone, Debug)]
+pub struct EffectiveSettings {
+    pub jobs:                Setting<NonZeroUsize>,
+    pub profile:             Setting<Profile>,
+    pub launcher:            Setting<Option<String>>,
+    pub color:               Setting<Color>,
+    pub max_source_files:    Setting<usize>,
+    pub max_warnings:        Setting<Option<usize>>,
+    pub stop_on_first_error: Setting<bool>,
+    pub cache_max_size:      Setting<Option<u64>>,
+    pub track_compiler:      Setting<bool>,
+    pub cc:                  Setting<String>,
+    /// Compiler for C++ sources, which only the toolchain of a target changes.
+    pub cxx:                 Setting<String>,
+    /// Archiver for static libraries, which only the toolchain of a target changes.
+    pub ar:                  Setting<String>,
+    /// Linker passed to the compiler with `-fuse-ld`, which is lld unless a target's toolchain says otherwise.
+    pub linker:              Setting<Option<String>>,
+    pub cflags:              Setting<Vec<String>>,
+    pub ldflags:             Setting<Vec<String>>,
+    pub include_dirs:        Setting<Vec<PathBuf>>,
+}
+
+/// Resolves each setting from the first layer that defines it, with the precedence command line > environment >
+/// project manifest > user config > built-in defaults.
+pub fn resolve_settings(cli: &BuildSettings, manifest: &BuildSettings, user: &BuildSettings) -> EffectiveSettings {
+    resolve_layers(cli, &BuildSettings::from_environment(), manifest, user)
+}
+
+/// Resolves the settings as [`resolve_settings`] does, with the environment layer given.
+fn resolve_layers(
+    cli: &BuildSettings,
+    environment: &BuildSettings,
+    manifest: &BuildSettings,
+    user: &BuildSettings,
+) -> EffectiveSettings {
+    fn pick<T: Clone>(
+        field: impl Fn(&BuildSettings) -> &Option<T>,
+        layers: [(&BuildSettings, Origin); 4],
+    ) -> Option<Setting<T>> {
+        layers.into_iter().find_map(|(layer, origin)| {
+            field(layer).clone().map(|value| Setting {
+                value,
+                origin,
+            })
+        })
+    }
+
+    fn default<T>(value: T) -> Setting<T> {
+        Setting {
+            value,
+            origin: Origin::Default,
+        }
+    }
+
+    let layers = [
+        (cli, Origin::CommandLine),
+        (environment, Origin::Environment),
+        (manifest, Origin::Manifest),
+        (user, Origin::UserConfig),
+    ];
+
+    EffectiveSettings {
+        jobs:                pick(|f| &f.jobs, layers)
+            .unwrap_or_else(|| default(available_parallelism().unwrap_or(NonZeroUsize::MIN))),
+        profile:             pick(|f| &f.profile, layers).unwrap_or_else(|| default(Profile::default())),
+        launcher:            pick(|f| &f.launcher, layers).map_or_else(
+            || default(None),
+            |f| Setting {
+                value:  Some(f.value),
+                origin: f.origin,
+            },
+        ),
+        color:               pick(|f| &f.color, layers).unwrap_or_else(|| default(Color::default())),
+        max_source_files:    pick(|f| &f.max_source_files, layers).unwrap_or_else(|| default(100_000)),
+        max_warnings:        pick(|f| &f.max_warnings, layers).map_or_else(
+            || default(None),
+            |f| Setting {
+                value:  Some(f.value),
+                origin: f.origin,
+            },
+        ),
+        stop_on_first_error: pick(|f| &f.stop_on_first_error, layers).unwrap_or_else(|| default(false)),
+        cache_max_size:      pick(|f| &f.cache_max_size, layers).map_or_else(
+            || default(None),
+            |f| Setting {
+                value:  Some(f.value),
+                origin: f.origin,
+            },
+        ),
+        track_compiler:      pick(|f| &f.track_compiler, layers).unwrap_or_else(|| default(true)),
+        cc:                  pick(|f| &f.cc, layers).unwrap_or_else(|| default("clang".to_owned())),
+        cxx:                 default("clang++".to_owned()),
+        ar:                  default("ar".to_owned()),
+        linker:              default(Some("lld".to_owned())),
+        cflags:              pick(|f| &f.cflags, layers).unwrap_or_else(|| default(Vec::new())),
+        ldflags:             pick(|f| &f.ldflags, layers).unwrap_or_else(|| default(Vec::new())),
+        include_dirs:        pick(|f| &f.include_dirs, layers).unwrap_or_else(|| default(Vec::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings that set `cc`, `jobs`, `launcher`, `color` and `profile`, all to values other than the defaults.
+    fn layer(name: &str, jobs: usize) -> BuildSettings {
+        BuildSettings {
+            cc: Some(format!("{}-cc", name)),
+            jobs: NonZeroUsize::new(jobs),
+            launcher: Some(format!("{}-launcher", name)),
+            color: Some(Color::Never),
+            profile: Some(Profile::Release),
+            ..BuildSettings::default()
+        }
+    }
+
+    /// Resolves the layers and checks that `cc`, `jobs`, `launcher`, `color` and `profile` all came from `origin`,
+    /// whose `cc` is `cc`.
+    fn assert_from(layers: [&BuildSettings; 4], origin: Origin, cc: &str) {
+        let [cli, environment, manifest, user] = layers;
+        let settings = resolve_layers(cli, environment, manifest, user);
+
+        assert_eq!((settings.cc.value.as_str(), settings.cc.origin), (cc, origin));
+        for setting in [
+            settings.jobs.origin,
+            settings.launcher.origin,
+            settings.color.origin,
+            settings.profile.origin,
+        ] {
+            assert_eq!(setting, origin);
+        }
+    }
+
+    /// The environment only ever sets `cc`.
+    fn environment() -> BuildSettings {
+        BuildSettings {
+            cc: Some("environment-cc".to_owned()),
+            ..BuildSettings::default()
+        }
+    }
+
+    #[test]
+    fn command_line_wins_over_every_other_layer() {
+        let (cli, environment, manifest, user) =
+            (layer("cli", 1), environment(), layer("manifest", 2), layer("user", 3));
+        assert_from([&cli, &environment, &manifest, &user], Origin::CommandLine, "cli-cc");
+
+        let settings = resolve_layers(&cli, &environment, &manifest, &user);
+        assert_eq!(settings.jobs.value.get(), 1);
+        assert_eq!(settings.launcher.value.as_deref(), Some("cli-launcher"));
+    }
+
+    #[test]
+    fn environment_wins_over_the_manifest_and_the_user_config() {
+        let (environment, manifest, user) = (environment(), layer("manifest", 2), layer("user", 3));
+        let settings = resolve_layers(&BuildSettings::default(), &environment, &manifest, &user);
+
+        assert_eq!(settings.cc.value, "environment-cc");
+        assert_eq!(settings.cc.origin, Origin::Environment);
+        assert_eq!(settings.jobs.origin, Origin::Manifest);
+    }
+
+    #[test]
+    fn manifest_wins_over_the_user_config() {
+        let (manifest, user) = (layer("manifest", 2), layer("user", 3));
+        assert_from(
+            [&BuildSettings::default(), &BuildSettings::default(), &manifest, &user],
+            Origin::Manifest,
+            "manifest-cc",
+        );
+
+        let settings = resolve_layers(&BuildSettings::default(), &BuildSettings::default(), &manifest, &user);
+        assert_eq!(settings.jobs.value.get(), 2);
+    }
+
+    #[test]
+    fn user_config_wins_over_the_defaults() {
+        let user = layer("user", 3);
+        assert_from(
+            [
+                &BuildSettings::default(),
+                &BuildSettings::default(),
+                &BuildSettings::default(),
+                &user,
+            ],
+            Origin::UserConfig,
+            "user-cc",
+        );
+
+        let settings = resolve_layers(
+            &BuildSettings::default(),
+            &BuildSettings::default(),
+            &BuildSettings::default(),
+            &user,
+        );
+        assert_eq!(settings.profile.value, Profile::Release);
+        assert_eq!(settings.color.value, Color::Never);
+    }
+
+    #[test]
+    fn defaults_apply_when_no_layer_sets_a_setting() {
+        let empty = BuildSettings::default();
+        assert_from([&empty, &empty, &empty, &empty], Origin::Default, "clang");
+
+        let settings = resolve_layers(&empty, &empty, &empty, &empty);
+        assert_eq!(settings.profile.value, Profile::Debug);
+        assert_eq!(settings.launcher.value, None);
+        assert_eq!(settings.color.value, Color::Auto);
+    }
+
+    #[test]
+    fn defaults_sets_are_read_by_their_year_and_differ() {
+        let set = |year: &str| {
+            toml::from_str::<BTreeMap<String, Defaults>>(&format!("defaults = \"{}\"", year)).unwrap()["defaults"]
+        };
+        let (old, new) = (set("2023"), set("2025"));
+
+        assert_ne!(old.flags(), new.flags());
+        assert!(new.flags().contains(&"-Wshadow") && !old.flags().contains(&"-Wshadow"));
+        assert!(toml::from_str::<BTreeMap<String, Defaults>>("defaults = \"2024\"").is_err());
+    }
+
+    #[test]
+    fn each_setting_is_picked_on_its_own() {
+        let cli = BuildSettings {
+            jobs: NonZeroUsize::new(1),
+            ..BuildSettings::default()
+        };
+        let manifest = BuildSettings {
+            profile: Some(Profile::Release),
+            ..BuildSettings::default()
+        };
+        let settings = resolve_layers(&cli, &BuildSettings::default(), &manifest, &layer("user", 3));
+
+        assert_eq!(settings.jobs.origin, Origin::CommandLine);
+        assert_eq!(settings.profile.origin, Origin::Manifest);
+        assert_eq!(settings.launcher.origin, Origin::UserConfig);
+        assert_eq!(settings.track_compiler.origin, Origin::Default);
+    }
+}
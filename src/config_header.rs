@@ -0,0 +1,155 @@
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::config::Check;
+use crate::config::ConfigHeader;
+use crate::config::Define;
+use crate::executable::Executable;
+use crate::status;
+use crate::status::Verb;
+use crate::storage;
+
+/// Writes the project's generated header. The header records a hash of everything it was generated from on its first
+/// line, and is left untouched, without rerunning any checks, while that hash still matches.
+pub struct GenerateConfigHeader {
+    pub header:      ConfigHeader,
+    pub output:      PathBuf,
+    /// The compiler the checks are run with.
+    pub compiler:    String,
+    /// The linker function checks link with, as for [`crate::compiler::LinkObjectsToBinary`].
+    pub linker:      Option<String>,
+    /// Identifies the compiler the checks are run with, so changing compilers reruns them.
+    pub fingerprint: String,
+}
+
+impl GenerateConfigHeader {
+    fn marker(&self) -> String {
+        let mut inputs = serde_json::to_vec(&self.header).unwrap_or_default();
+        inputs.extend_from_slice(self.fingerprint.as_bytes());
+
+        format!(
+            "/* Generated by Loki from inputs {:x}. Do not edit. */",
+            xxh3_64(&inputs)
+        )
+    }
+
+    fn generate(&self, marker: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut header = format!("{}\n#ifndef LOKI_CONFIG_H\n#define LOKI_CONFIG_H\n\n", marker);
+
+        for (name, define) in &self.header.defines {
+            match define {
+                Define::Flag(true) => writeln!(header, "#define {} 1", name)?,
+                Define::Flag(false) => writeln!(header, "/* #undef {} */", name)?,
+                Define::Integer(value) => writeln!(header, "#define {} {}", name, value)?,
+                Define::Text(value) => writeln!(header, "#define {} {}", name, string_literal(value))?,
+            }
+        }
+
+        let work_directory = self.output.parent().unwrap();
+        for (name, check) in &self.header.checks {
+            let found = run_check(check, &self.compiler, self.linker.as_deref(), work_directory)?;
+            status::status(
+                Verb::Checking,
+                format!(
+                    "{}: {}",
+                    match check {
+                        Check::Header(header) => format!("header <{}>", header),
+                        Check::Function(function) => format!("function {}()", function),
+                    },
+                    if found { "found" } else { "not found" }
+                ),
+            );
+
+            match found {
+                true => writeln!(header, "#define {} 1", name)?,
+                false => writeln!(header, "/* #undef {} */", name)?,
+            }
+        }
+
+        header.push_str("\n#endif\n");
+
+        Ok(header)
+    }
+}
+
+impl Executable for GenerateConfigHeader {
+    fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
+        let marker = self.marker();
+
+        let current = fs::read_to_string(&self.output).unwrap_or_default();
+        if current.lines().next() == Some(marker.as_str()) {
+            return Ok(0);
+        }
+
+        fs::create_dir_all(self.output.parent().unwrap())?;
+        let header = self.generate(&marker)?;
+        storage::write_atomically(&self.output, header.as_bytes())?;
+
+        status::status(Verb::Generated, self.output.display());
+
+        Ok(0)
+    }
+
+    fn describe(&self) -> String {
+        format!("generate {}", self.output.display())
+    }
+}
+
+/// Runs a feature check by compiling, and for functions linking, a small program read from standard input.
+fn run_check(
+    check: &Check,
+    compiler: &str,
+    linker: Option<&str>,
+    work_directory: &Path,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let mut command = Command::new(compiler);
+    let program = match check {
+        Check::Header(header) => {
+            command.args(["-fsyntax-only", "-x", "c", "-"]);
+            format!("#include <{}>\n", header)
+        },
+        Check::Function(function) => {
+            command.args(linker.map(|f| format!("-fuse-ld={}", f)));
+            command
+                .args(["-x", "c", "-o"])
+                .arg(work_directory.join("check.out"))
+                .arg("-");
+            // Declared without a prototype so the check doesn't depend on which header declares the function.
+            format!("char {}();\nint main(void) {{ return {}(); }}\n", function, function)
+        },
+    };
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(program.as_bytes())?;
+    let found = child.wait()?.success();
+    _ = fs::remove_file(work_directory.join("check.out"));
+
+    Ok(found)
+}
+
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            character => literal.push(character),
+        }
+    }
+    literal.push('"');
+
+    literal
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// How far a file's modification time may be ahead of the clock before it is taken for clock skew, which leaves room
+/// for file systems that round timestamps up.
+const SKEW_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// A prerequisite that makes an output stale.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Modified after the output, or no longer existing.
+    Modified(PathBuf),
+    /// Modified later than now, so comparing it with the output says nothing. The clock of the machine or of the file
+    /// server it was written on is off.
+    Future(PathBuf),
+}
+
+/// Returns where the dependency file of `output` is written, next to it.
+pub fn path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_owned();
+    name.push(".d");
+    output.with_file_name(name)
+}
+
+/// Reads the prerequisites of a Make rule as written by `-MMD`: the source followed by every header it includes,
+/// with backslashes escaping spaces and ending continued lines. Returns `None` if the file isn't such a rule.
+pub fn parse(contents: &str) -> Option<Vec<PathBuf>> {
+    let contents = contents.replace("\\\r\n", " ").replace("\\\n", " ");
+    // Only the first rule matters; `-MP` would add phony rules for every header after it.
+    let rule = contents.lines().next()?;
+    let (_target, prerequisites) = rule
+        .split_once(": ")
+        .or_else(|| rule.strip_suffix(':').map(|f| (f, "")))?;
+
+    let mut paths = Vec::new();
+    let mut current = String::new();
+    let mut characters = prerequisites.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' if characters.peek() == Some(&' ') => current.push(characters.next()?),
+            '$' if characters.peek() == Some(&'$') => current.push(characters.next()?),
+            character if character.is_whitespace() =>
+                if !current.is_empty() {
+                    paths.push(PathBuf::from(std::mem::take(&mut current)));
+                },
+            character => current.push(character),
+        }
+    }
+    if !current.is_empty() {
+        paths.push(PathBuf::from(current));
+    }
+
+    Some(paths)
+}
+
+/// Returns a prerequisite recorded in `depfile` that is dated in the future, or else the first one that was modified
+/// after `modified` or no longer exists. A dependency file that is missing or can't be parsed is an error, since
+/// nothing can be said about the output then.
+pub fn newer_prerequisite(depfile: &Path, modified: SystemTime) -> io::Result<Option<Change>> {
+    let prerequisites = parse(&fs::read_to_string(depfile)?)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a dependency file"))?;
+
+    let mut newer = None;
+    for prerequisite in prerequisites {
+        match fs::metadata(&prerequisite).and_then(|f| f.modified()) {
+            Ok(time) if in_the_future(time) => return Ok(Some(Change::Future(prerequisite))),
+            Ok(time) if time <= modified => (),
+            _ => _ = newer.get_or_insert(Change::Modified(prerequisite)),
+        }
+    }
+
+    Ok(newer)
+}
+
+/// Returns whether `time` is later than now by more than file systems round timestamps.
+pub fn in_the_future(time: SystemTime) -> bool {
+    time.duration_since(SystemTime::now()).is_ok_and(|f| f > SKEW_TOLERANCE)
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Diagnostics {
+    pub warnings: usize,
+    pub errors:   usize,
+}
+
+impl Diagnostics {
+    /// Counts the diagnostics in the captured standard error of a GCC-compatible compiler.
+    pub fn parse(stderr: &str) -> Self {
+        strip_escapes(stderr)
+            .lines()
+            .fold(Self::default(), |mut diagnostics, line| {
+                if line.contains(": warning: ") {
+                    diagnostics.warnings += 1;
+                } else if line.contains(": error: ") || line.contains(": fatal error: ") {
+                    diagnostics.errors += 1;
+                }
+
+                diagnostics
+            })
+    }
+}
+
+/// Returns where the number of warnings compiling `output` produced is recorded, next to it, so they can be counted
+/// again while `output` is up to date.
+pub fn warnings_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_owned();
+    name.push(".warnings");
+    output.with_file_name(name)
+}
+
+/// Reads the number of warnings recorded at `path`, or `None` if it is missing or corrupt.
+pub fn recorded_warnings(path: &Path) -> Option<usize> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Symbol problems recognized in the output of a GNU, LLVM, or Apple linker.
+#[derive(Clone, Debug, Default)]
+pub struct LinkDiagnostics {
+    pub undefined: BTreeSet<String>,
+    pub duplicate: BTreeSet<String>,
+}
+
+impl LinkDiagnostics {
+    pub fn parse(stderr: &str) -> Self {
+        let stderr = strip_escapes(stderr);
+        let mut diagnostics = Self::default();
+        let mut lines = stderr.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            if let Some(rest) = after(line, "undefined reference to ").or_else(|| after(line, "undefined symbol: ")) {
+                diagnostics.undefined.insert(unquote(rest));
+            } else if let Some(rest) = duplicate_symbol(line) {
+                diagnostics
+                    .duplicate
+                    .insert(unquote(rest.split(" in:").next().unwrap()));
+            } else if line.starts_with("Undefined symbols for architecture") {
+                // Apple's linker lists each symbol on its own indented line: `  "_foo", referenced from:`.
+                while let Some(symbol) = lines.next_if(|f| f.starts_with(' ')) {
+                    if let Some(symbol) = symbol.trim().strip_suffix(", referenced from:") {
+                        diagnostics.undefined.insert(unquote(symbol));
+                    }
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.undefined.is_empty() && self.duplicate.is_empty()
+    }
+
+    /// Renders a short, human-readable summary of the problems, with a hint for resolving each kind.
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+
+        if !self.undefined.is_empty() {
+            summary.push_str("  undefined symbols:\n");
+            for symbol in &self.undefined {
+                summary.push_str(&format!("    {}\n", symbol));
+            }
+            summary.push_str(
+                "  hint: check that every function is defined somewhere in the project, or did you forget to link a \
+                 library that provides it?\n",
+            );
+        }
+
+        if !self.duplicate.is_empty() {
+            summary.push_str("  duplicate symbols:\n");
+            for symbol in &self.duplicate {
+                summary.push_str(&format!("    {}\n", symbol));
+            }
+            summary.push_str(
+                "  hint: a symbol is defined in more than one source file; mark definitions local to a file as \
+                 `static`, or define shared ones in exactly one file and declare them `extern` in headers\n",
+            );
+        }
+
+        summary
+    }
+}
+
+fn after<'a>(line: &'a str, pattern: &str) -> Option<&'a str> {
+    line.find(pattern).map(|f| &line[f + pattern.len()..])
+}
+
+/// Returns what follows the duplicate-symbol marker of GNU ld, lld, or Apple's linker in `line`, if it has one.
+fn duplicate_symbol(line: &str) -> Option<&str> {
+    // Apple's linker quotes the symbol, which tells it apart from its closing `1 duplicate symbol for ...`.
+    let apple = || after(line, "duplicate symbol ").filter(|f| f.starts_with(['\'', '"']));
+
+    after(line, "multiple definition of ")
+        .or_else(|| after(line, "duplicate symbol: "))
+        .or_else(apple)
+}
+
+/// Strips the quoting linkers put around symbol names, along with anything following the closing quote.
+fn unquote(symbol: &str) -> String {
+    let symbol = symbol.trim_start_matches(['`', '\'', '"', '‘']);
+    symbol
+        .split(['\'', '"', '’', ';'])
+        .next()
+        .unwrap_or(symbol)
+        .trim()
+        .to_owned()
+}
+
+/// Removes the ANSI escape sequences tools use to color their output.
+fn strip_escapes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            // Control sequences end with a byte in the range '@'..='~', e.g. "\x1B[01;35m" or "\x1B[K".
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(set: &BTreeSet<String>) -> Vec<&str> {
+        set.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn gnu_ld_undefined_and_multiple_definitions() {
+        let diagnostics = LinkDiagnostics::parse(
+            "/usr/bin/ld: main.o: in function `main':\nmain.c:(.text+0x5): undefined reference to \
+             `greet'\n/usr/bin/ld: main.c:(.text+0x11): undefined reference to `farewell'\n/usr/bin/ld: b.o: in \
+             function `helper':\nb.c:(.text+0x0): multiple definition of `helper'; a.o:a.c:(.text+0x0): first defined \
+             here\ncollect2: error: ld returned 1 exit status\n",
+        );
+
+        assert_eq!(symbols(&diagnostics.undefined), ["farewell", "greet"]);
+        assert_eq!(symbols(&diagnostics.duplicate), ["helper"]);
+    }
+
+    #[test]
+    fn gnu_ld_with_unicode_quotes() {
+        let diagnostics = LinkDiagnostics::parse("main.c:(.text+0x5): undefined reference to ‘greet’\n");
+        assert_eq!(symbols(&diagnostics.undefined), ["greet"]);
+    }
+
+    #[test]
+    fn lld_undefined_and_duplicate_symbols() {
+        let diagnostics = LinkDiagnostics::parse(
+            "ld.lld: error: undefined symbol: greet\n>>> referenced by main.c\n>>>               \
+             main.o:(main)\nld.lld: error: duplicate symbol: helper\n>>> defined at a.c\n>>>            \
+             a.o:(helper)\n>>> defined at b.c\n>>>            b.o:(.text+0x0)\nclang: error: linker command failed \
+             with exit code 1 (use -v to see invocation)\n",
+        );
+
+        assert_eq!(symbols(&diagnostics.undefined), ["greet"]);
+        assert_eq!(symbols(&diagnostics.duplicate), ["helper"]);
+    }
+
+    #[test]
+    fn colored_lld_output() {
+        let diagnostics = LinkDiagnostics::parse(
+            "\x1B[0;1;31mld.lld: error: \x1B[0mundefined symbol: greet\n>>> referenced by main.c\n",
+        );
+        assert_eq!(symbols(&diagnostics.undefined), ["greet"]);
+    }
+
+    #[test]
+    fn apple_ld_undefined_and_duplicate_symbols() {
+        let diagnostics = LinkDiagnostics::parse(
+            "Undefined symbols for architecture arm64:\n  \"_greet\", referenced from:\n      _main in main.o\n  \
+             \"_farewell\", referenced from:\n      _main in main.o\nld: symbol(s) not found for architecture \
+             arm64\nduplicate symbol '_helper' in:\n    a.o\n    b.o\nld: 1 duplicate symbol for architecture arm64\n",
+        );
+
+        assert_eq!(symbols(&diagnostics.undefined), ["_farewell", "_greet"]);
+        assert_eq!(symbols(&diagnostics.duplicate), ["_helper"]);
+    }
+
+    #[test]
+    fn other_failures_have_no_symbols() {
+        let diagnostics = LinkDiagnostics::parse("/usr/bin/ld: cannot find -lnope: No such file or directory\n");
+        assert!(diagnostics.is_empty());
+    }
+}
@@ -1,8 +1,11 @@
 use std::error::Error;
 use std::fs;
+use std::io;
 use std::path::PathBuf;
 
 use crate::executable::Executable;
+use crate::status;
+use crate::status::Verb;
 
 pub struct CreateDirectory {
     pub directory: PathBuf,
@@ -10,6 +13,140 @@ pub struct CreateDirectory {
 
 impl Executable for CreateDirectory {
     fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
-        Ok(fs::create_dir_all(&self.directory).map(|_| 0)?)
+        match fs::create_dir_all(&self.directory) {
+            Ok(()) => Ok(0),
+            // Another build, or another node of this one, may create the directory at the same time.
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && self.directory.is_dir() => Ok(0),
+            Err(error) => Err(match error.kind() {
+                io::ErrorKind::PermissionDenied =>
+                    format!("permission denied while creating {}", self.directory.display()),
+                io::ErrorKind::ReadOnlyFilesystem =>
+                    format!("cannot create {} on a read-only filesystem", self.directory.display()),
+                io::ErrorKind::AlreadyExists | io::ErrorKind::NotADirectory => format!(
+                    "cannot create {} because a file is in the way",
+                    self.directory.display()
+                ),
+                _ => format!("failed to create {}: {}", self.directory.display(), error),
+            }
+            .into()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("create directory {}", self.directory.display())
+    }
+}
+
+/// Removes a directory and everything in it, refusing to remove anything outside of `within`. Symbolic links are
+/// removed rather than followed, so nothing they point to is touched.
+pub struct RemoveDirectory {
+    pub directory: PathBuf,
+    pub within:    PathBuf,
+}
+
+impl Executable for RemoveDirectory {
+    fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
+        let metadata = match fs::symlink_metadata(&self.directory) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                status::status(Verb::Skipped, format!("{} does not exist", self.directory.display()));
+                return Ok(0);
+            },
+            Err(error) => return Err(format!("cannot remove {}: {}", self.directory.display(), error).into()),
+        };
+
+        if metadata.is_symlink() {
+            fs::remove_file(&self.directory)?;
+            status::status(
+                Verb::Removed,
+                format!(
+                    "{}, a symbolic link, leaving what it points to",
+                    self.directory.display()
+                ),
+            );
+            return Ok(0);
+        }
+
+        let parent = self.directory.parent().unwrap_or(&self.directory).canonicalize()?;
+        if !parent.starts_with(self.within.canonicalize()?) {
+            return Err(format!(
+                "refusing to remove {}, which is outside of {}",
+                self.directory.display(),
+                self.within.display()
+            )
+            .into());
+        }
+
+        fs::remove_dir_all(&self.directory).map_err(|f| match f.kind() {
+            io::ErrorKind::PermissionDenied => format!("permission denied while removing {}", self.directory.display()),
+            _ => format!("failed to remove {}: {}", self.directory.display(), f),
+        })?;
+        status::status(Verb::Removed, self.directory.display());
+
+        Ok(0)
+    }
+
+    fn describe(&self) -> String {
+        format!("remove directory {}", self.directory.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_creators_of_the_same_directory_all_succeed() {
+        let root = tempfile::tempdir().unwrap();
+        let directory = root.path().join("target/debug/obj");
+        let barrier = Arc::new(Barrier::new(32));
+
+        let workers = (0..32)
+            .map(|_| {
+                let (directory, barrier) = (directory.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    CreateDirectory {
+                        directory,
+                    }
+                    .execute()
+                    .map_err(|f| f.to_string())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), Ok(0));
+        }
+        assert!(directory.is_dir());
+    }
+
+    #[test]
+    fn existing_directory_is_success() {
+        let root = tempfile::tempdir().unwrap();
+
+        let result = CreateDirectory {
+            directory: root.path().to_owned(),
+        }
+        .execute();
+        assert_eq!(result.unwrap(), 0);
+    }
+
+    #[test]
+    fn file_in_the_way_is_reported() {
+        let root = tempfile::tempdir().unwrap();
+        let directory = root.path().join("obj");
+        fs::write(&directory, "").unwrap();
+
+        let error = CreateDirectory {
+            directory,
+        }
+        .execute()
+        .unwrap_err();
+        assert!(error.to_string().contains("because a file is in the way"), "{}", error);
     }
 }
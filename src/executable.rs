@@ -1,16 +1,40 @@
 use std::error::Error;
 use std::process::Command;
+use std::process::ExitStatus;
 
-pub trait Executable {
+use itertools::Itertools;
+
+use crate::summary::BuildSummary;
+
+pub trait Executable: Send {
     fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>>;
+
+    /// Names what executing this does, for tracing the build graph.
+    fn describe(&self) -> String;
+
+    /// Whether this is one of the numbered steps in the build's progress, which announce themselves with
+    /// [`status::step`](crate::status::step). Bookkeeping like creating directories isn't.
+    fn is_step(&self) -> bool {
+        false
+    }
+
+    /// How much of the `jobs` budget executing this takes while it runs.
+    fn weight(&self) -> usize {
+        1
+    }
+
+    /// Records the outcome of a finished execution, which exited with `code`, into the build summary.
+    fn summarize(&self, _code: i32, _summary: &mut BuildSummary) {}
 }
 
 impl Executable for Command {
     fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
-        self.spawn()?
-            .wait()?
-            .code()
-            .ok_or_else(|| todo!("child process signal handling"))
+        let status = self.spawn()?.wait()?;
+        exit_code(self, status)
+    }
+
+    fn describe(&self) -> String {
+        format!("run {}", display_command(self))
     }
 }
 
@@ -18,4 +42,30 @@ impl Executable for () {
     fn execute(&mut self) -> Result<i32, Box<dyn Error + Send + Sync>> {
         Ok(0)
     }
+
+    fn describe(&self) -> String {
+        String::from("nothing")
+    }
+}
+
+/// Returns the exit code of `command`, which finished with `status`, or an error naming the program if a signal
+/// ended it instead.
+pub fn exit_code(command: &Command, status: ExitStatus) -> Result<i32, Box<dyn Error + Send + Sync>> {
+    status.code().ok_or_else(|| {
+        format!(
+            "{} was terminated ({})",
+            command.get_program().to_string_lossy(),
+            status
+        )
+        .into()
+    })
+}
+
+/// Renders a command line the way it would be typed into a shell, for showing to the user.
+pub fn display_command(command: &Command) -> String {
+    [&[command.get_program()], &command.get_args().collect_vec()[..]]
+        .concat()
+        .iter()
+        .map(|f| f.to_string_lossy())
+        .join(" ")
 }
@@ -0,0 +1,82 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::Report;
+
+use crate::compiler::CSourceToObject;
+
+pub struct Include {
+    pub depth:  usize,
+    pub path:   PathBuf,
+    pub system: bool,
+}
+
+pub struct IncludeGraph {
+    pub includes: Vec<Include>,
+    /// Diagnostics for includes the compiler failed to resolve.
+    pub failures: Vec<String>,
+}
+
+/// Traces the transitive includes of `source` with the compiler's `-H` header trace, using the same flags as the
+/// build. Headers outside of `project_directory` are considered system headers.
+pub fn trace(source: &CSourceToObject, project_directory: &Path) -> Result<IncludeGraph, Report> {
+    let mut command = source.command();
+    command.arg("-H").arg("-fsyntax-only").arg(&source.input);
+
+    let output = command.output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let includes = stderr
+        .lines()
+        .filter_map(|line| {
+            let path = line.trim_start_matches('.');
+            let depth = line.len() - path.len();
+
+            (depth > 0 && path.starts_with(' ')).then(|| {
+                let path = PathBuf::from(path.trim_start());
+                let system = !path
+                    .canonicalize()
+                    .is_ok_and(|canonical| canonical.starts_with(project_directory));
+
+                Include {
+                    depth,
+                    path,
+                    system,
+                }
+            })
+        })
+        .collect();
+
+    let failures = stderr
+        .lines()
+        .filter(|line| line.contains("fatal error: ") || (line.contains(": error: ") && line.contains("not found")))
+        .map(str::to_owned)
+        .collect();
+
+    Ok(IncludeGraph {
+        includes,
+        failures,
+    })
+}
+
+impl IncludeGraph {
+    pub fn print(&self, source: &Path) {
+        println!("{}", source.display());
+
+        for include in &self.includes {
+            println!(
+                "{}{}{}",
+                "  ".repeat(include.depth),
+                include.path.display(),
+                if include.system { " (system)" } else { "" }
+            );
+        }
+
+        if !self.failures.is_empty() {
+            println!("\nFailed includes:");
+            for failure in &self.failures {
+                println!("  {}", failure);
+            }
+        }
+    }
+}
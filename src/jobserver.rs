@@ -0,0 +1,151 @@
+use std::env;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::fd::RawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::Arc;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+/// A client of the GNU make jobserver an outer `make -j` passes down in `MAKEFLAGS`, which hands out one token per job
+/// that may run besides the one every process started by make may always run.
+#[derive(Debug)]
+pub struct Client {
+    /// Opened without blocking, so asking for a token never waits for one.
+    read:  File,
+    write: File,
+}
+
+/// A token taken from the jobserver, which is given back when it is dropped, however the job it was taken for ends.
+#[derive(Debug)]
+pub struct Token {
+    client: Arc<Client>,
+    byte:   u8,
+}
+
+impl Client {
+    /// Connects to the jobserver `MAKEFLAGS` names, if it names one. Fails if the jobserver can't be opened, as when
+    /// make didn't pass its file descriptors on because the recipe isn't marked with `+`.
+    pub fn from_environment() -> Result<Option<Arc<Self>>, Report> {
+        let makeflags = env::var("MAKEFLAGS").unwrap_or_default();
+        let Some(auth) = auth(&makeflags) else {
+            return Ok(None);
+        };
+
+        let unavailable = |error: io::Error| {
+            eyre!(
+                "the jobserver '{}' in MAKEFLAGS isn't available ({}); mark the make recipe with '+', or pass \
+                 --no-jobserver",
+                auth,
+                error
+            )
+        };
+        let client = match auth.strip_prefix("fifo:") {
+            Some(path) => {
+                let fifo = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .custom_flags(libc::O_NONBLOCK)
+                    .open(path)
+                    .map_err(unavailable)?;
+                Client {
+                    write: fifo.try_clone().map_err(unavailable)?,
+                    read:  fifo,
+                }
+            },
+            None => {
+                let (read, write) = auth
+                    .split_once(',')
+                    .and_then(|(read, write)| Some((read.parse().ok()?, write.parse().ok()?)))
+                    .ok_or_else(|| eyre!("malformed jobserver '{}' in MAKEFLAGS", auth))?;
+                Client {
+                    read:  reopen(read, false).map_err(unavailable)?,
+                    write: reopen(write, true).map_err(unavailable)?,
+                }
+            },
+        };
+
+        Ok(Some(Arc::new(client)))
+    }
+
+    /// Takes a token if one is free right away.
+    pub fn try_acquire(self: &Arc<Self>) -> Option<Token> {
+        let mut poll = libc::pollfd {
+            fd:      self.read.as_raw_fd(),
+            events:  libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `poll` is given exactly one valid `pollfd`.
+        if unsafe { libc::poll(&mut poll, 1, 0) } != 1 {
+            return None;
+        }
+
+        let mut byte = [0];
+        loop {
+            match (&self.read).read(&mut byte) {
+                Ok(1) =>
+                    return Some(Token {
+                        client: Arc::clone(self),
+                        byte:   byte[0],
+                    }),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                // Without a token, fewer jobs run at once, which is never wrong.
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        _ = (&self.client.write).write_all(&[self.byte]);
+    }
+}
+
+/// Returns the value of the last `--jobserver-auth` in `makeflags`, or of `--jobserver-fds` as older versions of make
+/// call it.
+fn auth(makeflags: &str) -> Option<&str> {
+    makeflags.split_whitespace().rev().find_map(|f| {
+        f.strip_prefix("--jobserver-auth=")
+            .or_else(|| f.strip_prefix("--jobserver-fds="))
+    })
+}
+
+/// Opens the end of the jobserver's pipe that make passed down as `fd` again, so it can be read without blocking
+/// without affecting the other processes sharing it. Where `/dev/fd` duplicates `fd` instead, as on macOS, the read
+/// after `poll` finds a token can still block if another process takes it first, until a token is given back.
+fn reopen(fd: RawFd, write: bool) -> io::Result<File> {
+    // SAFETY: `fcntl` with `F_GETFD` only checks whether `fd` is open.
+    if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("file descriptor {} is closed", fd),
+        ));
+    }
+
+    OpenOptions::new()
+        .read(!write)
+        .write(write)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(format!("/dev/fd/{}", fd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_is_the_last_jobserver_in_makeflags() {
+        assert_eq!(auth("-j --jobserver-auth=3,4"), Some("3,4"));
+        assert_eq!(
+            auth("-j --jobserver-fds=3,4 --jobserver-auth=fifo:/tmp/jobs"),
+            Some("fifo:/tmp/jobs")
+        );
+        assert_eq!(auth("k -- FOO=bar"), None);
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+use crate::config::Package;
+use crate::config::Profile;
+use crate::config::ProjectKind;
+
+/// Where everything belonging to a project lives on disk when building one of its profiles. All paths are derived
+/// here, so every command agrees on where artifacts are.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub project_directory: PathBuf,
+    /// Where sources are discovered unless the manifest says otherwise.
+    pub source_directory:  PathBuf,
+    /// The project's own headers, which every source can include.
+    pub header_directory:  PathBuf,
+    /// Sources of tests, each of which `loki test` builds into its own binary.
+    pub test_directory:    PathBuf,
+    /// `target`, which holds what every build of the project produces, for the host and for each target.
+    pub build_directory:   PathBuf,
+    /// Everything built, `target`, or `target/<triple>` when cross-compiling, so host and cross builds never overwrite
+    /// each other.
+    pub target_directory:  PathBuf,
+    /// Everything built with the selected profile, `<target>/<profile>`, so profiles never overwrite each other.
+    pub profile_directory: PathBuf,
+    pub object_directory:  PathBuf,
+    /// Where `loki test` links the test binaries.
+    pub test_binaries:     PathBuf,
+    /// Headers generated by the build, which every source can include.
+    pub include_directory: PathBuf,
+    /// Loki's own bookkeeping for the selected profile, such as cached probe results.
+    pub state_directory:   PathBuf,
+    pub package_directory: PathBuf,
+    /// Manifest describing the artifacts of the last successful build.
+    pub artifacts:         PathBuf,
+    /// Compilation database describing how every source of the last build is compiled.
+    pub compile_commands:  PathBuf,
+    /// Whether the build is for macOS, as the target triple says when cross-compiling and the host otherwise.
+    pub targets_macos:     bool,
+}
+
+impl Layout {
+    /// Lays out the project at `project_directory` for building `profile`, for the host or the target `triple`.
+    pub fn new(project_directory: &Path, profile: Profile, triple: Option<&str>) -> Self {
+        let build_directory = project_directory.join("target");
+        let target_directory = match triple {
+            Some(triple) => build_directory.join(triple),
+            None => build_directory.clone(),
+        };
+        let profile_directory = target_directory.join(profile.name());
+
+        Self {
+            project_directory: project_directory.to_owned(),
+            source_directory: project_directory.join("src"),
+            header_directory: project_directory.join("include"),
+            test_directory: project_directory.join("tests"),
+            object_directory: profile_directory.join("obj"),
+            test_binaries: profile_directory.join("tests"),
+            include_directory: target_directory.join("include"),
+            state_directory: profile_directory.join(".loki"),
+            package_directory: target_directory.join("package"),
+            artifacts: profile_directory.join("artifacts.json"),
+            compile_commands: target_directory.join("compile_commands.json"),
+            build_directory,
+            target_directory,
+            profile_directory,
+            targets_macos: match triple {
+                Some(triple) => triple.contains("apple"),
+                None => cfg!(target_os = "macos"),
+            },
+        }
+    }
+
+    /// Resolves the directories the package's sources are discovered in, which are `src` unless configured
+    /// otherwise. Directories that don't exist or that overlap are rejected.
+    pub fn source_directories(&self, package: &Package) -> Result<Vec<PathBuf>, Report> {
+        let configured = match (&package.source_dir, &package.source_dirs) {
+            (Some(_), Some(_)) => return Err(eyre!("only one of 'source-dir' and 'source-dirs' may be set")),
+            (Some(directory), None) => vec![directory.clone()],
+            (None, Some(directories)) if directories.is_empty() => return Err(eyre!("'source-dirs' is empty")),
+            (None, Some(directories)) => directories.clone(),
+            (None, None) => return Ok(vec![self.source_directory.clone()]),
+        };
+
+        let mut directories: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for directory in configured {
+            let resolved = self.project_directory.join(&directory);
+            let canonical = resolved
+                .canonicalize()
+                .map_err(|_| eyre!("source directory '{}' does not exist", directory.display()))?;
+
+            for (other, other_canonical) in &directories {
+                if canonical.starts_with(other_canonical) || other_canonical.starts_with(&canonical) {
+                    return Err(eyre!(
+                        "source directories '{}' and '{}' overlap",
+                        other.display(),
+                        directory.display()
+                    ));
+                }
+            }
+
+            directories.push((directory, canonical));
+        }
+
+        // Collecting the components drops `.`, so `source-dir = "."` doesn't show up in every path.
+        Ok(directories
+            .into_iter()
+            .map(|(directory, _)| self.project_directory.join(directory).components().collect())
+            .collect())
+    }
+
+    /// Path of the linked binary for a package named `name`.
+    pub fn binary(&self, name: &str) -> PathBuf {
+        self.profile_directory.join(name)
+    }
+
+    /// Path of what building `package` produces, which depends on its type.
+    pub fn artifact(&self, package: &Package) -> PathBuf {
+        match package.kind {
+            ProjectKind::Binary => self.binary(&package.name),
+            ProjectKind::StaticLib => self.profile_directory.join(format!("lib{}.a", package.name)),
+            ProjectKind::SharedLib => self.profile_directory.join(format!(
+                "lib{}.{}",
+                package.name,
+                if self.targets_macos { "dylib" } else { "so" }
+            )),
+        }
+    }
+}
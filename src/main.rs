@@ -1,36 +1,382 @@
+mod artifacts;
+mod cache;
+mod cli;
+mod compile_commands;
 mod compiler;
 mod config;
+mod config_header;
+mod depfile;
+mod diagnostic;
 mod directory;
 mod executable;
+mod includes;
+mod jobserver;
+mod layout;
 mod node;
+mod package;
+mod pkg_config;
+mod probe;
+mod safety;
+mod scaffold;
+mod sources;
+mod status;
+mod storage;
+mod summary;
+mod targets;
+mod testing;
+mod toolchain;
+mod watch;
 
-use std::cell::RefCell;
 use std::env::args;
 use std::env::current_dir;
-use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::process;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
+use artifacts::Artifact;
+use artifacts::ArtifactKind;
+use artifacts::Artifacts;
+use cache::ObjectCache;
+use cli::BuildOptions;
+use cli::NewOptions;
+use cli::PackageOptions;
+use cli::RunOptions;
+use cli::ScaffoldOptions;
+use cli::SingleFileOptions;
+use cli::TestOptions;
+use color_eyre::eyre::eyre;
 use color_eyre::Report;
+use compile_commands::CompileCommand;
+use compiler::ArchiveObjectsToStaticLib;
+use compiler::BundleDebugInfo;
 use compiler::CSourceToObject;
+use compiler::Emit;
+use compiler::Language;
 use compiler::LinkObjectsToBinary;
+use config_header::GenerateConfigHeader;
 use directory::CreateDirectory;
-use itertools::Itertools;
+use directory::RemoveDirectory;
+use executable::display_command;
+use executable::Executable;
+use layout::Layout;
+use node::Jobs;
 use node::Node;
-use walkdir::WalkDir;
+use pkg_config::Packages;
+use probe::Fingerprint;
+use probe::Probes;
+use status::Verb;
+use summary::BuildSummary;
+use testing::Test;
+use toml_edit::value;
+use toml_edit::DocumentMut;
+use toml_edit::Item;
+use toml_edit::Table;
+use xxhash_rust::xxh3::xxh3_64;
 
+use crate::config::resolve_settings;
+use crate::config::BuildProfile;
+use crate::config::BuildSettings;
+use crate::config::Configuration;
+use crate::config::EffectiveSettings;
+use crate::config::Optimization;
+use crate::config::Origin;
+use crate::config::Profile;
 use crate::config::Project;
+use crate::config::ProjectKind;
+use crate::config::Standard;
+use crate::sources::Reason;
 
 fn main() -> Result<(), Report> {
     color_eyre::install()?;
 
     let args = args().collect::<Vec<_>>();
     match args.get(1).map(|f| f.as_str()) {
+        Some("build") if cli::is_source_file(args.get(2)) => {
+            let options = SingleFileOptions::parse(&args[2], &args[3..])?;
+            let (summary, _) = build_single_file(&options)?;
+            summary.emit(options.build.output_format)?;
+            exit_on_failure(&summary);
+        },
+
+        Some("run") if cli::is_source_file(args.get(2)) => {
+            let options = SingleFileOptions::parse(&args[2], &args[3..])?;
+
+            let (summary, binary) = build_single_file(&options)?;
+            summary.emit(options.build.output_format)?;
+            exit_on_failure(&summary);
+
+            run_binary(Command::new(&binary).args(&options.arguments))?;
+        },
+
+        Some("run") => {
+            let options = RunOptions::parse(&args[2..])?;
+            if options.build.emit != Emit::Object {
+                return Err(eyre!(
+                    "'run' needs a binary and can't emit assembly instead. See '--help' for usage."
+                ));
+            }
+            let project = load_project(&find_project_directory(&options.build)?)?;
+            if project.package.kind != ProjectKind::Binary {
+                return Err(eyre!(
+                    "'run' needs a binary, but {} is a {} project",
+                    project.package.name,
+                    ArtifactKind::of(project.package.kind).name()
+                ));
+            }
+
+            let (summary, binary) = build_project(&options.build)?;
+            summary.emit(options.build.output_format)?;
+            exit_on_failure(&summary);
+
+            let project_directory = find_project_directory(&options.build)?;
+            run_binary(
+                Command::new(&binary)
+                    .args(&options.arguments)
+                    .current_dir(project_directory),
+            )?;
+        },
+
+        Some("test") => {
+            let options = TestOptions::parse(&args[2..])?;
+            if options.build.emit != Emit::Object {
+                return Err(eyre!(
+                    "'test' needs binaries and can't emit assembly instead. See '--help' for usage."
+                ));
+            }
+
+            let (summary, tests) = build_tests(&options)?;
+            if tests.is_empty() {
+                status::status(Verb::Skipped, "testing, since no tests found in 'tests'");
+                return Ok(());
+            }
+            summary.emit(options.build.output_format)?;
+            exit_on_failure(&summary);
+
+            if testing::run(&tests, &find_project_directory(&options.build)?)? > 0 {
+                process::exit(1);
+            }
+        },
+
+        Some("watch") => {
+            let options = BuildOptions::parse(&args[2..])?;
+            watch_project(&options)?;
+        },
+
         Some("build") => {
-            build_project()?;
+            let options = BuildOptions::parse(&args[2..])?;
+            let (summary, _) = build_project(&options)?;
+            summary.emit(options.output_format)?;
+            exit_on_failure(&summary);
+        },
+
+        Some("package") => {
+            let options = PackageOptions::parse(&args[2..])?;
+            let (mut summary, _) = build_project(&options.build)?;
+
+            if summary.success {
+                let (layout, project, _) = open_project(&options.build)?;
+                let artifacts = Artifacts::load(&layout.artifacts)?;
+
+                summary.package = Some(package::package(&layout, &project, &artifacts, options.format)?);
+            }
+
+            summary.emit(options.build.output_format)?;
+            exit_on_failure(&summary);
+        },
+
+        Some("list") => {
+            let options = BuildOptions::parse(&args[2..])?;
+            let (layout, project, _) = open_project(&options)?;
+
+            targets::print(&layout, &targets::targets(&layout, &project), options.output_format)?;
+        },
+
+        Some("why") => {
+            let Some(path) = args.get(2) else {
+                return Err(eyre!("'why' requires a source or object. See '--help' for usage."));
+            };
+            let options = BuildOptions::parse(&args[3..])?;
+
+            explain_inclusion(&options, Path::new(path))?;
+        },
+
+        Some("includes") => {
+            let Some(source) = args.get(2) else {
+                return Err(eyre!("'includes' requires a source file. See '--help' for usage."));
+            };
+            let options = BuildOptions::parse(&args[3..])?;
+
+            let (layout, project, settings) = open_project(&options)?;
+            status::configure(settings.color.value, options.verbosity);
+
+            let identity = Fingerprint::of(&settings.cc.value);
+            let conditional_flags = conditional_flags(&settings, &layout, &options, &identity)?;
+            let profile = project.profile(settings.profile.value);
+            let packages = pkg_config::query(&project.dependencies.pkg_config)?;
+
+            let source = PathBuf::from(source);
+            let mut compile = compile_node(
+                &project,
+                &profile,
+                &settings,
+                &conditional_flags,
+                &layout,
+                source.clone(),
+                Emit::Object,
+            );
+            compile.flags.extend(packages.cflags);
+
+            includes::trace(&compile, &layout.project_directory.canonicalize()?)?.print(&source);
+        },
+
+        Some("clean") => {
+            let (what, rest) = match args.get(2).map(|f| f.as_str()) {
+                Some(what @ ("--state" | "--cache" | "--objects")) => (Some(what), &args[3..]),
+                _ => (None, &args[2..]),
+            };
+            let options = BuildOptions::parse(rest)?;
+
+            match what {
+                Some(what) => {
+                    let (layout, ..) = open_project(&options)?;
+                    match what {
+                        "--state" => remove_directory(&layout, &layout.state_directory)?,
+                        _ => cache::clean(&layout)?,
+                    }
+                },
+                None => {
+                    // The target directory holds every profile, so which one is selected doesn't matter.
+                    let layout = Layout::new(
+                        &find_project_directory(&options)?,
+                        Profile::default(),
+                        options.target.as_deref(),
+                    );
+                    remove_directory(&layout, &layout.target_directory)?;
+                },
+            }
+        },
+
+        Some(command @ ("new" | "init")) => {
+            let options = NewOptions::parse(&args[2..])?;
+
+            let current_directory = current_dir()?;
+            match (command, options.name) {
+                ("new", Some(name)) => {
+                    let directory = current_directory.join(&name);
+                    scaffold::new_project(&directory, &name, options.flat)?;
+                },
+                ("new", None) => {
+                    return Err(eyre!("'new' requires a project name. See '--help' for usage."));
+                },
+                (_, name) => {
+                    let name = name.unwrap_or_else(|| {
+                        current_directory
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .into_owned()
+                    });
+                    scaffold::new_project(&current_directory, &name, options.flat)?;
+                },
+            }
+        },
+
+        Some(command @ ("add-file" | "remove-file")) => {
+            let Some(name) = args.get(2) else {
+                return Err(eyre!("'{}' requires a file name. See '--help' for usage.", command));
+            };
+            let options = ScaffoldOptions::parse(name, &args[3..])?;
+
+            let (layout, project, _) = open_project(&options.build)?;
+            let source_directory = &layout.source_directories(&project.package)?[0];
+            match command {
+                "add-file" =>
+                    scaffold::add_file(&layout, source_directory, &options.name, options.header, options.force)?,
+                _ => scaffold::remove_file(&layout, source_directory, &options.name, options.yes)?,
+            }
+        },
+
+        Some("env") => {
+            let options = BuildOptions::parse(&args[2..])?;
+            let (project, project_directory) = match find_project_directory(&options) {
+                Ok(project_directory) => (load_project(&project_directory)?, project_directory),
+                Err(_) => (Project::default(), current_dir()?),
+            };
+            let settings = project_settings(&options, &project, &project_directory)?;
+
+            println!("jobs     = {} ({})", settings.jobs.value, settings.jobs.origin);
+            println!(
+                "profile  = {} ({})",
+                settings.profile.value.name(),
+                settings.profile.origin
+            );
+            println!(
+                "launcher = {} ({})",
+                settings.launcher.value.as_deref().unwrap_or("none"),
+                settings.launcher.origin
+            );
+            println!("color    = {:?} ({})", settings.color.value, settings.color.origin);
+            println!(
+                "target   = {} ({})",
+                options.target.as_deref().unwrap_or("host"),
+                if options.target.is_some() {
+                    Origin::CommandLine
+                } else {
+                    Origin::Default
+                }
+            );
+            println!("cc       = {} ({})", settings.cc.value, settings.cc.origin);
+            println!("cxx      = {} ({})", settings.cxx.value, settings.cxx.origin);
+            println!("ar       = {} ({})", settings.ar.value, settings.ar.origin);
+            println!(
+                "linker   = {} ({})",
+                settings.linker.value.as_deref().unwrap_or("default"),
+                settings.linker.origin
+            );
+            println!("cflags   = {:?} ({})", settings.cflags.value, settings.cflags.origin);
+            println!("ldflags  = {:?} ({})", settings.ldflags.value, settings.ldflags.origin);
+            println!(
+                "include-dirs = {:?} ({})",
+                settings.include_dirs.value, settings.include_dirs.origin
+            );
+            println!(
+                "max-source-files = {} ({})",
+                settings.max_source_files.value, settings.max_source_files.origin
+            );
+            println!(
+                "stop-on-first-error = {} ({})",
+                settings.stop_on_first_error.value, settings.stop_on_first_error.origin
+            );
+            println!(
+                "track-compiler = {} ({})",
+                settings.track_compiler.value, settings.track_compiler.origin
+            );
+            println!(
+                "cache-max-size = {} ({})",
+                settings
+                    .cache_max_size
+                    .value
+                    .map_or_else(|| "none".to_owned(), |f| f.to_string()),
+                settings.cache_max_size.origin
+            );
+            println!(
+                "max-warnings = {} ({})",
+                settings
+                    .max_warnings
+                    .value
+                    .map_or_else(|| "none".to_owned(), |f| f.to_string()),
+                settings.max_warnings.origin
+            );
         },
 
         Some("-v" | "--version") => {
@@ -55,7 +401,69 @@ fn main() -> Result<(), Report> {
                 Copyright (c) 2023 Reperak\n\
                 \n\
                 Subcommands:\n    \
-                    build           Build a Loki project\n\
+                    build           Build a Loki project\n    \
+                    build <file.c>  Build a single source file outside of any project\n    \
+                    run             Build a Loki project and run its binary with the arguments after '--'\n    \
+                    run <file.c>    Build a single source file and run it with the arguments after '--'\n    \
+                    test [name]     Build the tests in 'tests', each with the project but its 'main', and run them,\n                    \
+                                    or only the test <name>\n    \
+                    watch           Build a Loki project, then again whenever a source, header or loki.toml changes,\n                    \
+                                    until interrupted with Ctrl-C\n    \
+                    package         Build a Loki project and archive it for distribution\n    \
+                    list            List what building the project produces, without building it\n    \
+                    why <path>      Explain why a source or object is part of the build and how it is compiled\n    \
+                    includes <src>  Show the include tree of a source file\n    \
+                    env             Show the effective build settings and where they come from\n    \
+                    clean           Remove the 'target' directory and everything built into it, or with '--target'\n                    \
+                                    only 'target/<triple>'\n    \
+                    clean --state   Remove Loki's cached state in 'target/<profile>/.loki', keeping objects and\n                    \
+                                    binaries\n    \
+                    clean --objects Remove the cached objects in 'target/<profile>/obj', keeping binaries; also\n                    \
+                                    '--cache'. Both only clean the profile selected, and with '--target' the one in\n                    \
+                                    'target/<triple>/<profile>'\n    \
+                    new <name>      Create a project for a binary called <name> in a new directory; '--flat'\n                    \
+                                    puts 'main.c' next to 'loki.toml' instead of in 'src'\n    \
+                    init [name]     Create a project in the current directory, named after it unless <name> is given\n    \
+                    add-file <name> Create 'src/<name>.c', and with '--header' also 'include/<name>.h'; '--force'\n                    \
+                                    overwrites existing files\n    \
+                    remove-file <name>\n                    \
+                                    Delete the files 'add-file' creates, after confirmation unless '--yes'\n\
+                \n\
+                Build options:\n    \
+                    --output <fmt>      Print the build summary as 'human' (default) or 'json'\n    \
+                    -j, --jobs <n>      Run concurrent steps whose weights add up to at most <n>\n    \
+                    --release           Build the release profile into 'target/release' instead of the debug one\n    \
+                    --debug             Build the debug profile, even if 'profile' selects the release one\n    \
+                    --target <triple>   Cross-compile for <triple> into 'target/<triple>', with the toolchain of\n                        \
+                                        '[target.<triple>]' in loki.toml or else '<triple>-gcc'\n    \
+                    --launcher <cmd>    Prefix compiler invocations with a launcher, e.g. ccache\n    \
+                    --cc <compiler>     Compile C sources with <compiler> instead of clang, overriding $CC\n    \
+                    --color <when>      Color diagnostics: 'auto' (default), 'always', or 'never'\n    \
+                    --max-warnings <n>  Fail the build if the project produces more than <n> warnings\n    \
+                    --update-warning-budget\n                        \
+                                        Set 'max-warnings' in loki.toml to the number of warnings produced\n    \
+                    --stop-on-first-error\n                        \
+                                        Stop each source at its first error\n    \
+                    -k, --keep-going    Build everything that doesn't depend on a failed step, instead of stopping\n                        \
+                                        at the first failure\n    \
+                    --no-probes         Assume the compiler supports no optional flags instead of testing\n    \
+                    --no-jobserver      Ignore the jobserver of an outer 'make -j' and run up to '--jobs' instead\n    \
+                    -v, --verbose       Show commands and full tool output; '-vv' also shows why sources are\n                        \
+                                        rebuilt, '-vvv' also traces the build graph\n    \
+                    -q, --quiet         Show only warnings and errors\n    \
+                    --emit=asm          Compile each source to assembly instead of linking a binary\n    \
+                    --project-dir <dir> Use the project in <dir> instead of the nearest one\n    \
+                    --allow-root-project\n                        \
+                                        Allow '/', system directories, or your home directory as a project\n\
+                \n\
+                Single-file options:\n    \
+                    -O<level>           Optimization level, as for the compiler (default: 0)\n    \
+                    --define <macro>    Define a macro, as 'NAME' or 'NAME=VALUE'\n    \
+                    --cflag <flag>      Pass an extra flag to the compiler\n    \
+                    --lib <name>        Link against a library, e.g. 'm'\n\
+                \n\
+                Package options:\n    \
+                    --format <fmt>      Archive format: 'tar.gz' (default) or 'zip'\n\
                 \n\
                 Usage:\n    \
                     --help          Show this text and exit\n    \
@@ -64,98 +472,894 @@ fn main() -> Result<(), Report> {
         },
 
         _ => {
-            println!("Unknown command/flag '{}'. See '--help' for usage.", args[1]);
+            return Err(eyre!("unknown command/flag '{}'. See '--help' for usage.", args[1]));
         },
     }
 
     Ok(())
 }
 
-fn build_project() -> Result<(), Report> {
-    let (loki_toml, source_directory, target_directory, object_directory) = current_dir()?
-        .ancestors()
-        .map(PathBuf::from)
-        .map(|project_directory| {
-            (
-                project_directory.join("loki.toml"),
-                project_directory.join("src"),
-                project_directory.join("target"),
-                project_directory.join("target/obj"),
-            )
+/// Finds the project to operate on: the one given with `--project-dir`, or else the nearest directory at or above the
+/// current one that contains a `loki.toml`.
+fn find_project_directory(options: &BuildOptions) -> Result<PathBuf, Report> {
+    let project_directory = match &options.project_directory {
+        Some(project_directory) if project_directory.join("loki.toml").exists() => project_directory.clone(),
+        Some(project_directory) => return Err(eyre!("no loki.toml found in {}", project_directory.display())),
+        None => current_dir()?
+            .ancestors()
+            .find(|project_directory| project_directory.join("loki.toml").exists())
+            .map(PathBuf::from)
+            .ok_or(io::Error::new(
+                io::ErrorKind::NotFound,
+                "loki project directory not found",
+            ))?,
+    };
+
+    safety::check_project_directory(&project_directory, options.allow_root_project)?;
+
+    Ok(project_directory)
+}
+
+fn load_project(project_directory: &Path) -> Result<Project, Report> {
+    Ok(toml::from_str(&io::read_to_string(BufReader::new(File::open(
+        project_directory.join("loki.toml"),
+    )?))?)?)
+}
+
+/// Sets `max-warnings` in the `[build]` table of the project's manifest, keeping the rest of the file as written.
+fn update_warning_budget(project_directory: &Path, warnings: usize) -> Result<(), Report> {
+    let path = project_directory.join("loki.toml");
+    let mut manifest = fs::read_to_string(&path)?.parse::<DocumentMut>()?;
+
+    manifest
+        .entry("build")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| eyre!("'build' in {} is not a table", path.display()))?
+        .insert("max-warnings", value(warnings as i64));
+
+    storage::write_atomically(&path, manifest.to_string().as_bytes())?;
+    status::status(
+        Verb::Updated,
+        format!("'max-warnings' to {} in {}", warnings, path.display()),
+    );
+
+    Ok(())
+}
+
+/// What every build of a project shares before anything is linked: the resolved manifest and settings, and the nodes
+/// that every compile depends on.
+struct ProjectBuild {
+    layout: Layout,
+    project: Project,
+    profile: BuildProfile,
+    settings: EffectiveSettings,
+    conditional_flags: Vec<String>,
+    packages: Packages,
+    /// The C compiler, fingerprinted once for everything in the build that needs to identify it.
+    identity: Fingerprint,
+    /// The compiler fingerprint hashed into every object name, if `track-compiler` is set.
+    compiler: Option<String>,
+    create_profile_directory_node: Arc<Mutex<Node>>,
+    /// Nodes that have to run before any source is compiled, such as creating the object directory.
+    setup_nodes: Vec<Arc<Mutex<Node>>>,
+}
+
+impl ProjectBuild {
+    fn load(options: &BuildOptions) -> Result<Self, Report> {
+        let (layout, project, settings) = open_project(options)?;
+        let profile = project.profile(settings.profile.value);
+        status::configure(settings.color.value, options.verbosity);
+        safety::warn_if_superuser();
+
+        let identity = Fingerprint::of(&settings.cc.value);
+        let conditional_flags = conditional_flags(&settings, &layout, options, &identity)?;
+        let packages = pkg_config::query(&project.dependencies.pkg_config)?;
+
+        let create_profile_directory_node = Arc::new(Mutex::new(Node {
+            executable: Box::new(CreateDirectory {
+                directory: layout.profile_directory.clone(),
+            }),
+            children:   Vec::new(),
+        }));
+
+        let create_object_directory_node = Arc::new(Mutex::new(Node {
+            executable: Box::new(CreateDirectory {
+                directory: layout.object_directory.clone(),
+            }),
+            children:   Vec::new(),
+        }));
+
+        let config_header_node = match &project.config_header {
+            Some(header) => Some(Arc::new(Mutex::new(Node {
+                executable: Box::new(GenerateConfigHeader {
+                    header:      header.clone(),
+                    output:      layout.include_directory.join(&header.name),
+                    compiler:    settings.cc.value.clone(),
+                    linker:      settings.linker.value.clone(),
+                    fingerprint: identity.get()?.to_owned(),
+                }),
+                children:   Vec::new(),
+            }))),
+            None => None,
+        };
+
+        let compiler = tracked_compiler(&layout, &settings, &identity)?;
+        let setup_nodes = [
+            Some(Arc::clone(&create_profile_directory_node)),
+            Some(create_object_directory_node),
+            config_header_node,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        Ok(Self {
+            layout,
+            project,
+            profile,
+            settings,
+            conditional_flags,
+            packages,
+            identity,
+            compiler,
+            create_profile_directory_node,
+            setup_nodes,
         })
-        .filter(|(loki_toml, ..)| loki_toml.exists())
-        .last()
-        .ok_or(io::Error::new(
-            io::ErrorKind::NotFound,
-            "loki project directory not found",
-        ))?;
+    }
 
-    let project: Project = toml::from_str(&io::read_to_string(BufReader::new(File::open(loki_toml)?))?)?;
+    /// Creates the node that compiles `source`, along with the object it writes and how the compilation database
+    /// describes it.
+    fn compile(
+        &self,
+        source: PathBuf,
+        options: &BuildOptions,
+    ) -> Result<(Arc<Mutex<Node>>, PathBuf, CompileCommand), Report> {
+        let mut cs2o = compile_node(
+            &self.project,
+            &self.profile,
+            &self.settings,
+            &self.conditional_flags,
+            &self.layout,
+            source,
+            options.emit,
+        );
+        cs2o.flags.extend(self.packages.cflags.iter().cloned());
+        cs2o.extra_inputs.extend(self.compiler.clone());
+        cs2o.replay_warnings |= options.update_warning_budget;
+        let object = cs2o.output()?;
+        let command = CompileCommand::new(&self.layout.project_directory, &cs2o)?;
 
-    let source_files = WalkDir::new(source_directory)
+        let node = Arc::new(Mutex::new(Node {
+            executable: Box::new(cs2o),
+            children:   self.setup_nodes.clone(),
+        }));
+
+        Ok((node, object, command))
+    }
+
+    /// Creates the link of `inputs` into the binary `output` with the project's flags and libraries, skipped while
+    /// what is recorded at `record` is unchanged.
+    fn link(&self, language: Language, inputs: Vec<PathBuf>, output: PathBuf, record: PathBuf) -> LinkObjectsToBinary {
+        LinkObjectsToBinary {
+            compiler: driver(&self.settings, language),
+            linker: self.settings.linker.value.clone(),
+            optimization: self.profile.link_optimization(),
+            inputs,
+            flags: [&self.settings.ldflags.value[..], &self.profile.ldflags].concat(),
+            library_dirs: self
+                .project
+                .dependencies
+                .lib_dirs
+                .iter()
+                .map(|f| self.layout.project_directory.join(f))
+                .collect(),
+            package_libs: self.packages.libs.clone(),
+            libraries: self.project.dependencies.libs.clone(),
+            shared: false,
+            output,
+            record: Some(record),
+            relinked: false,
+        }
+    }
+}
+
+/// Returns the language to link sources in `paths` with, which is C++ if any of them is C++.
+fn link_language<'a>(mut paths: impl Iterator<Item = &'a PathBuf>) -> Language {
+    match paths.any(|f| Language::of(f) == Some(Language::Cpp)) {
+        true => Language::Cpp,
+        false => Language::C,
+    }
+}
+
+/// Builds the project `options` points at, returning the summary and the path of the binary or library, which only
+/// exists if the build succeeded without emitting assembly instead.
+fn build_project(options: &BuildOptions) -> Result<(BuildSummary, PathBuf), Report> {
+    let start = Instant::now();
+
+    let build = ProjectBuild::load(options)?;
+    let (layout, project, settings) = (&build.layout, &build.project, &build.settings);
+
+    let source_files = sources::sources(layout, &project.package, settings.max_source_files.value)?;
+
+    let cache = ObjectCache::load(layout);
+    let link_language = link_language(source_files.iter().map(|f| &f.path));
+    let mut objects = Vec::new();
+    let mut compile_commands = Vec::new();
+    let c2so_nodes = source_files
         .into_iter()
-        .map(|dir| dir.unwrap().into_path())
-        .filter(|path| path.extension().is_some_and(|d| d == "c"))
-        .collect_vec();
+        .map(|source| {
+            let (node, object, command) = build.compile(source.path, options)?;
+            objects.push(object);
+            compile_commands.push(command);
 
-    let create_target_directory_node = Rc::new(RefCell::new(Node {
-        executable: Box::new(CreateDirectory {
-            directory: target_directory.clone(),
-        }),
-        children:   Vec::new(),
-    }));
+            Ok(node)
+        })
+        .collect::<Result<Vec<_>, Report>>()?;
+
+    // Written before anything is compiled, so editors can make sense of a project even while it doesn't build.
+    fs::create_dir_all(&layout.target_directory)?;
+    compile_commands::write(&layout.compile_commands, &compile_commands)?;
+
+    if options.emit == Emit::Asm {
+        let mut summary = BuildSummary::default();
+        node::execute(
+            Arc::new(Mutex::new(Node {
+                executable: Box::new(()),
+                children:   c2so_nodes,
+            })),
+            &mut summary,
+            !options.keep_going,
+            &jobs(options, settings),
+        )
+        .map_err(|f| eyre!(f))?;
+        cache.update(
+            layout,
+            &objects,
+            (summary.skipped, summary.compiled),
+            settings.cache_max_size.value,
+        )?;
+        status::status(Verb::Skipped, "linking, since assembly was requested");
+        summary.finish(start.elapsed());
 
-    let create_object_directory_node = Rc::new(RefCell::new(Node {
+        return Ok((summary, layout.artifact(&project.package)));
+    }
+
+    let artifact = layout.artifact(&project.package);
+    let children = [&c2so_nodes[..], &[Arc::clone(&build.create_profile_directory_node)]].concat();
+    let lo2b_node = match project.package.kind {
+        ProjectKind::StaticLib => Arc::new(Mutex::new(Node {
+            executable: Box::new(ArchiveObjectsToStaticLib {
+                archiver: settings.ar.value.clone(),
+                inputs:   objects.clone(),
+                output:   artifact.clone(),
+                record:   Some(layout.state_directory.join("archived")),
+                archived: false,
+            }),
+            children,
+        })),
+        ProjectKind::Binary | ProjectKind::SharedLib => Arc::new(Mutex::new(Node {
+            executable: Box::new(LinkObjectsToBinary {
+                shared: project.package.kind == ProjectKind::SharedLib,
+                ..build.link(
+                    link_language,
+                    objects.clone(),
+                    artifact.clone(),
+                    layout.state_directory.join("linked"),
+                )
+            }),
+            children,
+        })),
+    };
+
+    // On macOS the linker leaves debug info in the objects, and dsymutil collects it into a bundle.
+    let bundles_debug_info = project.package.kind != ProjectKind::StaticLib && layout.targets_macos;
+    let lo2b_node = match project.configuration.split_debug && bundles_debug_info {
+        true => Arc::new(Mutex::new(Node {
+            executable: Box::new(BundleDebugInfo {
+                binary: artifact.clone(),
+            }),
+            children:   vec![lo2b_node],
+        })),
+        false => lo2b_node,
+    };
+
+    let mut summary = BuildSummary::default();
+    node::execute(lo2b_node, &mut summary, !options.keep_going, &jobs(options, settings)).map_err(|f| eyre!(f))?;
+    cache.update(
+        layout,
+        &objects,
+        (summary.skipped, summary.compiled),
+        settings.cache_max_size.value,
+    )?;
+
+    if options.update_warning_budget {
+        if summary.success {
+            update_warning_budget(&layout.project_directory, summary.warnings)?;
+        }
+    } else if let Some(budget) = settings.max_warnings.value {
+        summary.check_warning_budget(budget);
+    }
+
+    if summary.success {
+        Artifacts {
+            format_version: artifacts::FORMAT_VERSION,
+            profile:        settings.profile.value,
+            target:         match &options.target {
+                Some(triple) => triple.clone(),
+                None => host_triple(layout, &build.identity)?,
+            },
+            artifacts:      vec![Artifact::new(
+                ArtifactKind::of(project.package.kind),
+                &project.package.name,
+                &artifact,
+            )?],
+        }
+        .write(&layout.artifacts)?;
+    }
+
+    summary.finish(start.elapsed());
+
+    Ok((summary, artifact))
+}
+
+/// Builds the tests in `tests/`, or only the one `options` names. Each is linked with the objects of the project's
+/// sources, compiled once for all of them, except for sources named `main`, since every test has its own `main`.
+/// Returns the summary and the tests, which is empty if there are none.
+fn build_tests(options: &TestOptions) -> Result<(BuildSummary, Vec<Test>), Report> {
+    let start = Instant::now();
+
+    let build = ProjectBuild::load(&options.build)?;
+    let (layout, project, settings) = (&build.layout, &build.project, &build.settings);
+
+    let mut tests = testing::discover(layout)?;
+    if let Some(name) = &options.name {
+        tests.retain(|f| &f.name == name);
+        if tests.is_empty() {
+            return Err(eyre!("no test named '{}' in {}", name, layout.test_directory.display()));
+        }
+    }
+    if tests.is_empty() {
+        return Ok((BuildSummary::default(), tests));
+    }
+
+    let source_files = sources::sources(layout, &project.package, settings.max_source_files.value)?
+        .into_iter()
+        .map(|f| f.path)
+        .filter(|f| f.file_stem() != Some(OsStr::new("main")))
+        .collect::<Vec<_>>();
+
+    let cache = ObjectCache::load(layout);
+    let mut objects = Vec::new();
+    let c2so_nodes = source_files
+        .iter()
+        .map(|source| {
+            let (node, object, _) = build.compile(source.clone(), &options.build)?;
+            objects.push(object);
+
+            Ok(node)
+        })
+        .collect::<Result<Vec<_>, Report>>()?;
+
+    let create_test_directory_node = Arc::new(Mutex::new(Node {
         executable: Box::new(CreateDirectory {
-            directory: object_directory.clone(),
+            directory: layout.test_binaries.clone(),
         }),
         children:   Vec::new(),
     }));
 
-    let c2so_nodes = source_files
-        .clone()
-        .into_iter()
-        .map(|source| {
-            let cs2o = CSourceToObject {
-                configuration:    project.configuration,
-                input:            source,
-                object_directory: object_directory.clone(),
-            };
+    let mut test_objects = Vec::new();
+    let link_nodes = tests
+        .iter()
+        .map(|test| {
+            let (node, object, _) = build.compile(test.source.clone(), &options.build)?;
+            test_objects.push(object.clone());
 
-            let node = Node {
-                executable: Box::new(cs2o),
-                children:   vec![
-                    Rc::clone(&create_target_directory_node),
-                    Rc::clone(&create_object_directory_node),
-                ],
-            };
+            let link = build.link(
+                link_language(source_files.iter().chain([&test.source])),
+                [&objects[..], &[object]].concat(),
+                test.binary.clone(),
+                layout.state_directory.join(format!("test-{}", test.name)),
+            );
 
-            Rc::new(RefCell::new(node))
+            Ok(Arc::new(Mutex::new(Node {
+                executable: Box::new(link),
+                children:   [&c2so_nodes[..], &[node, Arc::clone(&create_test_directory_node)]].concat(),
+            })))
         })
-        .collect_vec();
+        .collect::<Result<Vec<_>, Report>>()?;
+
+    let mut summary = BuildSummary::default();
+    node::execute(
+        Arc::new(Mutex::new(Node {
+            executable: Box::new(()),
+            children:   link_nodes,
+        })),
+        &mut summary,
+        !options.build.keep_going,
+        &jobs(&options.build, settings),
+    )
+    .map_err(|f| eyre!(f))?;
+    cache.update(
+        layout,
+        &[objects, test_objects].concat(),
+        (summary.skipped, summary.compiled),
+        settings.cache_max_size.value,
+    )?;
+    summary.finish(start.elapsed());
+
+    Ok((summary, tests))
+}
+
+/// Builds a single source file outside of any project, with defaults suited to quick experiments. The build lives in
+/// the user-wide cache, in an entry keyed by the path of the source and the options it is built with, so an unchanged
+/// source is neither recompiled nor relinked. Returns the summary and the path of the binary.
+fn build_single_file(options: &SingleFileOptions) -> Result<(BuildSummary, PathBuf), Report> {
+    let start = Instant::now();
+
+    let mut settings = resolve_settings(
+        &options.build.settings,
+        &BuildSettings::default(),
+        &BuildSettings::load_user()?,
+    );
+    if let Some(triple) = &options.build.target {
+        toolchain::cross(&mut settings, triple, None, &current_dir()?)?;
+    }
+    status::configure(settings.color.value, options.build.verbosity);
+
+    let source = options
+        .source
+        .canonicalize()
+        .map_err(|f| eyre!("cannot read {}: {}", options.source.display(), f))?;
+    let key = xxh3_64(
+        format!(
+            "{}\0{:?}\0{:?}\0{:?}\0{:?}\0{:?}",
+            source.display(),
+            options.build.target,
+            options.level,
+            options.defines,
+            options.flags,
+            options.libraries
+        )
+        .as_bytes(),
+    );
+    let layout = Layout::new(
+        &config::user_cache_directory()
+            .ok_or_else(|| eyre!("could not determine the cache directory; set XDG_CACHE_HOME or HOME"))?
+            .join("single")
+            .join(format!("{:x}", key)),
+        Profile::Release,
+        None,
+    );
 
-    let lo2b_node = Rc::new(RefCell::new(Node {
+    let mut project = Project {
+        configuration: Configuration {
+            standard:     Standard::Gnu17,
+            cpp_standard: None,
+            optimization: Optimization {
+                level: options.level,
+                lto:   None,
+            },
+            link_level:   None,
+            split_debug:  false,
+        },
+        ..Project::default()
+    };
+    project.package.name = source.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    // The release profile is the one that takes its optimization from the configuration.
+    let profile = project.profile(Profile::Release);
+    let binary = layout.binary(&project.package.name);
+
+    let identity = Fingerprint::of(&settings.cc.value);
+    let conditional_flags = conditional_flags(&settings, &layout, &options.build, &identity)?;
+
+    let mut compile = compile_node(
+        &project,
+        &profile,
+        &settings,
+        &conditional_flags,
+        &layout,
+        source.clone(),
+        Emit::Object,
+    );
+    compile.flags = [&compile.flags[..], &["-g".to_owned()], &options.flags]
+        .concat()
+        .into_iter()
+        .chain(options.defines.iter().map(|f| format!("-D{}", f)))
+        .collect();
+    compile
+        .extra_inputs
+        .extend(tracked_compiler(&layout, &settings, &identity)?);
+
+    let object = compile.output()?;
+    let language = compile.language;
+    let mut summary = BuildSummary::default();
+
+    let compile_node = Arc::new(Mutex::new(Node {
+        executable: Box::new(compile),
+        children:   vec![Arc::new(Mutex::new(Node {
+            executable: Box::new(CreateDirectory {
+                directory: layout.object_directory.clone(),
+            }),
+            children:   Vec::new(),
+        }))],
+    }));
+
+    let link_node = Arc::new(Mutex::new(Node {
         executable: Box::new(LinkObjectsToBinary {
-            optimization: project.configuration.optimization,
-            inputs:       source_files,
-            output:       target_directory.join(project.package.name),
+            compiler:     driver(&settings, language),
+            linker:       settings.linker.value.clone(),
+            optimization: profile.link_optimization(),
+            inputs:       vec![object],
+            flags:        [&settings.ldflags.value[..], &profile.ldflags].concat(),
+            library_dirs: Vec::new(),
+            package_libs: Vec::new(),
+            libraries:    options.libraries.clone(),
+            shared:       false,
+            output:       binary.clone(),
+            record:       Some(layout.state_directory.join("linked")),
+            relinked:     false,
         }),
-        children:   [&c2so_nodes[..], &[
-            Rc::clone(&create_target_directory_node),
-            Rc::clone(&create_target_directory_node),
-        ]]
-        .concat(),
+        children:   vec![compile_node],
     }));
 
-    execute_node(lo2b_node).unwrap();
+    node::execute(
+        link_node,
+        &mut summary,
+        !options.build.keep_going,
+        &jobs(&options.build, &settings),
+    )
+    .map_err(|f| eyre!(f))?;
+    summary.finish(start.elapsed());
+
+    Ok((summary, binary))
+}
+
+/// Builds the project, then builds it again whenever one of its sources or headers, or its manifest, is created,
+/// modified, or deleted, until interrupted. A change made while a build runs is picked up by one more build after it.
+/// Every build loads the manifest again, so it may change between builds, and a build that can't even start, for
+/// example because the manifest is broken, is reported without ending the watch.
+fn watch_project(options: &BuildOptions) -> Result<(), Report> {
+    let project_directory = find_project_directory(options)?;
+    let watched = || watched_paths(&project_directory);
+    watch::handle_interrupts();
+
+    loop {
+        let before = watch::Snapshot::take(&watched());
+        let success = match watch_ignore(&project_directory).and_then(|_| build_project(options)) {
+            Ok((summary, _)) => {
+                summary.emit(options.output_format)?;
+                summary.success
+            },
+            Err(error) => {
+                status::status(Verb::Error, format!("{:#}", error));
+                false
+            },
+        };
+
+        if watch::interrupted() {
+            return Ok(());
+        }
+        status::status(
+            if success { Verb::Finished } else { Verb::Failed },
+            format!(
+                "[{}] build {}, watching for changes",
+                watch::timestamp(),
+                if success { "succeeded" } else { "failed" }
+            ),
+        );
+
+        let Some(changed) = watch::wait_for_change(&before, watched) else {
+            return Ok(());
+        };
+        status::status(Verb::Changed, changed.display());
+    }
+}
+
+/// What `loki watch` watches in the project at `project_directory`: its manifest, its source directories, and its
+/// headers. If the manifest can't be loaded, the default source directory is watched until it is fixed.
+fn watched_paths(project_directory: &Path) -> watch::Watched {
+    let layout = Layout::new(project_directory, Profile::default(), None);
+    let mut directories = load_project(project_directory)
+        .and_then(|f| layout.source_directories(&f.package))
+        .unwrap_or_else(|_| vec![layout.source_directory.clone()]);
+    directories.push(layout.header_directory.clone());
+
+    watch::Watched {
+        manifest: project_directory.join("loki.toml"),
+        directories,
+        ignored: layout.build_directory,
+        // Until a broken pattern is fixed, nothing is ignored; each build reports the pattern.
+        patterns: watch_ignore(project_directory).unwrap_or_default(),
+    }
+}
+
+/// Compiles the `watch-ignore` patterns of the project at `project_directory`, which are relative to it.
+fn watch_ignore(project_directory: &Path) -> Result<Vec<glob::Pattern>, Report> {
+    let prefix = glob::Pattern::escape(&project_directory.to_string_lossy());
+
+    load_project(project_directory)?
+        .package
+        .watch_ignore
+        .iter()
+        .map(|f| {
+            glob::Pattern::new(&format!("{}/{}", prefix, f))
+                .map_err(|error| eyre!("invalid pattern '{}' in 'watch-ignore': {}", f, error))
+        })
+        .collect()
+}
+
+/// Exits with status 1 if the build failed, so scripts and CI can tell. The summary has already said what failed.
+fn exit_on_failure(summary: &BuildSummary) {
+    if !summary.success {
+        process::exit(1);
+    }
+}
+
+/// Runs a built program with loki's standard streams, exiting with the program's exit code.
+fn run_binary(command: &mut Command) -> Result<(), Report> {
+    status::status(Verb::Running, display_command(command));
+
+    let status = command.status()?;
+    process::exit(status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0)));
+}
+
+/// Removes `directory` of the project at `layout`, which must be inside the project directory.
+fn remove_directory(layout: &Layout, directory: &Path) -> Result<(), Report> {
+    RemoveDirectory {
+        directory: directory.to_owned(),
+        within:    layout.project_directory.clone(),
+    }
+    .execute()
+    .map_err(|f| eyre!(f))?;
 
     Ok(())
 }
 
-fn execute_node(node: Rc<RefCell<Node>>) -> Result<i32, Box<dyn Error + Send + Sync>> {
-    for child in &node.borrow().children {
-        execute_node(Rc::clone(child))?;
+/// Prints why `path`, a source or an object, is part of the build: how the source was found, the command it is
+/// compiled with, and the targets that need its object.
+fn explain_inclusion(options: &BuildOptions, path: &Path) -> Result<(), Report> {
+    let (layout, project, settings) = open_project(options)?;
+    status::configure(settings.color.value, options.verbosity);
+
+    let wanted = path
+        .canonicalize()
+        .map_err(|_| eyre!("{} does not exist", path.display()))?;
+    let identity = Fingerprint::of(&settings.cc.value);
+    let conditional_flags = conditional_flags(&settings, &layout, options, &identity)?;
+    let packages = pkg_config::query(&project.dependencies.pkg_config)?;
+    let profile = project.profile(settings.profile.value);
+    // Fingerprinted without recording it, so a later build still reports a changed compiler.
+    let compiler = match settings.track_compiler.value {
+        true => Some(identity.get()?.to_owned()),
+        false => None,
+    };
+    let relative = |f: &Path| {
+        f.strip_prefix(&layout.project_directory)
+            .unwrap_or(f)
+            .display()
+            .to_string()
+    };
+
+    for source in sources::sources(&layout, &project.package, settings.max_source_files.value)? {
+        let mut compile = compile_node(
+            &project,
+            &profile,
+            &settings,
+            &conditional_flags,
+            &layout,
+            source.path.clone(),
+            Emit::Object,
+        );
+        compile.flags.extend(packages.cflags.iter().cloned());
+        compile.extra_inputs.extend(compiler.clone());
+        let object = compile.output()?;
+
+        if source.path.canonicalize()? != wanted && object.canonicalize().ok().as_ref() != Some(&wanted) {
+            continue;
+        }
+
+        println!("{}", relative(&source.path));
+        let reason = match &source.reason {
+            Reason::Listed => "listed in 'package.sources'".to_owned(),
+            Reason::Discovered(directory) => match relative(directory).as_str() {
+                "" => "found in source directory .".to_owned(),
+                directory => format!("found in source directory {}", directory),
+            },
+        };
+        println!("  included because it was {}", reason);
+        println!("  compiled to {} with", relative(&object));
+        println!("    {}", display_command(&compile.compile_command()?));
+        for target in targets::targets(&layout, &project) {
+            println!(
+                "  needed by {} ({}) {}",
+                target.name,
+                target.kind.name(),
+                relative(&target.path)
+            );
+        }
+
+        return Ok(());
+    }
+
+    Err(eyre!(
+        "{} is neither a source nor an object of {}",
+        path.display(),
+        project.package.name
+    ))
+}
+
+/// Probes the flags that are only passed to the compiler when it supports them.
+fn conditional_flags(
+    settings: &EffectiveSettings,
+    layout: &Layout,
+    options: &BuildOptions,
+    compiler: &Fingerprint,
+) -> Result<Vec<String>, Report> {
+    let mut probes = Probes::load(compiler, &layout.state_directory, !options.no_probes);
+
+    let requested = [compiler::color_flag(settings.color.value)];
+
+    let mut flags = Vec::new();
+    for flag in requested.into_iter().flatten() {
+        if probes.supports(flag)? {
+            flags.push(flag.to_owned());
+        }
+    }
+
+    probes.save()?;
+
+    Ok(flags)
+}
+
+/// Fingerprints the compiler when `track-compiler` is set, so that it can be hashed into every object name. The
+/// fingerprint is recorded in the state directory to explain the rebuild when it changes.
+fn tracked_compiler(
+    layout: &Layout,
+    settings: &EffectiveSettings,
+    compiler: &Fingerprint,
+) -> Result<Option<String>, Report> {
+    if !settings.track_compiler.value {
+        return Ok(None);
     }
 
-    node.borrow_mut().executable.execute()
+    let fingerprint = compiler.get()?.to_owned();
+    let record = layout.state_directory.join("compiler");
+    match fs::read_to_string(&record) {
+        Ok(previous) if previous == fingerprint => return Ok(Some(fingerprint)),
+        Ok(_) => status::status(
+            Verb::Checking,
+            "the compiler changed since the last build, so every object is rebuilt",
+        ),
+        Err(_) => {},
+    }
+
+    fs::create_dir_all(&layout.state_directory)?;
+    storage::write_atomically(&record, fingerprint.as_bytes())?;
+
+    Ok(Some(fingerprint))
+}
+
+/// Returns the triple `compiler` generates code for by default. Asking takes a process of its own, so the answer is
+/// recorded in the state directory along with the fingerprint of the compiler that gave it.
+fn host_triple(layout: &Layout, compiler: &Fingerprint) -> Result<String, Report> {
+    let fingerprint = compiler.get()?;
+    let record = layout.state_directory.join("host");
+    if let Ok(recorded) = fs::read_to_string(&record) {
+        match recorded.split_once('\n') {
+            Some((recorded, triple)) if recorded == fingerprint => return Ok(triple.to_owned()),
+            _ => {},
+        }
+    }
+
+    let triple = compiler::host_triple(compiler.compiler())?;
+    fs::create_dir_all(&layout.state_directory)?;
+    storage::write_atomically(&record, format!("{}\n{}", fingerprint, triple).as_bytes())?;
+
+    Ok(triple)
+}
+
+/// Returns the driver for `language`: the configured `cc` for C, and `clang++` or the target's C++ compiler for C++.
+fn driver(settings: &EffectiveSettings, language: Language) -> String {
+    match language {
+        Language::C => settings.cc.value.clone(),
+        Language::Cpp => settings.cxx.value.clone(),
+    }
+}
+
+/// Returns what limits how much of a build runs at once: the jobserver of an outer `make -j` if it passed one down and
+/// `options` don't ignore it, or else `jobs`.
+fn jobs(options: &BuildOptions, settings: &EffectiveSettings) -> Jobs {
+    if options.no_jobserver {
+        return Jobs::Budget(settings.jobs.value);
+    }
+    match jobserver::Client::from_environment() {
+        Ok(Some(client)) => Jobs::Jobserver(client),
+        Ok(None) => Jobs::Budget(settings.jobs.value),
+        Err(error) => {
+            status::status(
+                Verb::Warning,
+                format!("{}; running up to {} jobs instead", error, settings.jobs.value),
+            );
+            Jobs::Budget(settings.jobs.value)
+        },
+    }
+}
+
+/// Finds and loads the project `options` select, resolves its settings, and lays it out for the profile they choose.
+fn open_project(options: &BuildOptions) -> Result<(Layout, Project, EffectiveSettings), Report> {
+    let project_directory = find_project_directory(options)?;
+    let project = load_project(&project_directory)?;
+    let settings = project_settings(options, &project, &project_directory)?;
+    let layout = Layout::new(&project_directory, settings.profile.value, options.target.as_deref());
+
+    Ok((layout, project, settings))
+}
+
+/// Resolves the settings of a build of `project`, with the toolchain of the target `options` asks for, if any.
+fn project_settings(
+    options: &BuildOptions,
+    project: &Project,
+    project_directory: &Path,
+) -> Result<EffectiveSettings, Report> {
+    let mut settings = resolve_settings(&options.settings, &project.build, &BuildSettings::load_user()?);
+    if let Some(triple) = &options.target {
+        toolchain::cross(&mut settings, triple, project.target.get(triple), project_directory)?;
+    }
+
+    Ok(settings)
+}
+
+fn compile_node(
+    project: &Project,
+    profile: &BuildProfile,
+    settings: &EffectiveSettings,
+    conditional_flags: &[String],
+    layout: &Layout,
+    input: PathBuf,
+    emit: Emit,
+) -> CSourceToObject {
+    let language = Language::of(&input).unwrap_or_default();
+    let weight = project
+        .weights
+        .of(input.strip_prefix(&layout.project_directory).unwrap_or(&input));
+
+    CSourceToObject {
+        configuration: project.configuration,
+        optimization: profile.optimization,
+        debug: profile.debug,
+        split_dwarf: !layout.targets_macos,
+        defaults: project.package.defaults,
+        language,
+        compiler: driver(settings, language),
+        input,
+        object_directory: layout.object_directory.clone(),
+        include_directories: [
+            Some(&layout.header_directory).filter(|f| f.is_dir()),
+            project.config_header.as_ref().map(|_| &layout.include_directory),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect(),
+        launcher: settings.launcher.value.clone(),
+        conditional_flags: conditional_flags.to_vec(),
+        flags: settings
+            .include_dirs
+            .value
+            .iter()
+            .map(|f| format!("-I{}", layout.project_directory.join(f).display()))
+            .chain(settings.stop_on_first_error.value.then(|| "-Wfatal-errors".to_owned()))
+            .chain(settings.cflags.value.iter().cloned())
+            .chain(profile.cflags.iter().cloned())
+            .collect(),
+        // A generated header is rewritten only when what it defines changes, so the depfiles of the sources that
+        // include it find it newer than their objects, and sources that don't are left alone.
+        extra_inputs: Vec::new(),
+        emit,
+        position_independent: project.package.kind == ProjectKind::SharedLib,
+        replay_warnings: settings.max_warnings.value.is_some(),
+        weight,
+        report: None,
+    }
 }
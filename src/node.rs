@@ -1,9 +1,262 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::num::NonZeroUsize;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
 use crate::executable::Executable;
+use crate::jobserver::Client;
+use crate::jobserver::Token;
+use crate::status;
+use crate::status::Verb;
+use crate::status::Verbosity;
+use crate::summary::BuildSummary;
+
+/// What limits how much of the graph runs at once.
+pub enum Jobs {
+    /// Nodes whose weights add up to no more than this.
+    Budget(NonZeroUsize),
+    /// One node on the job that make started Loki as, and besides it nodes weighing as many tokens as are taken from
+    /// the jobserver. Tokens are given back as soon as what they were taken for has finished.
+    Jobserver(Arc<Client>),
+}
+
+/// How often a build waiting for a token from a jobserver asks it again.
+const TOKEN_POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 pub struct Node {
     pub executable: Box<dyn Executable>,
-    pub children:   Vec<Rc<RefCell<Node>>>,
+    pub children:   Vec<Arc<Mutex<Node>>>,
+}
+
+/// A node of the graph being executed, with the bookkeeping of when it can run.
+struct Task {
+    node:       Arc<Mutex<Node>>,
+    /// Tasks that depend on this one.
+    parents:    Vec<usize>,
+    /// Number of children that haven't finished yet.
+    pending:    usize,
+    /// Describes the node whose failure means this one can't run, if any below it failed.
+    blocked_by: Option<String>,
+    /// How much of the `jobs` budget the node takes while it runs.
+    weight:     usize,
+}
+
+/// Executes every node of the graph below and including `root` after its children, running nodes whose children have
+/// all finished at the same time as long as `jobs` leaves room for their weights. Nodes start in the order they
+/// become ready, so a heavy node waits for enough of the budget rather than being overtaken by lighter ones, and a node
+/// heavier than the whole budget runs alone. A node shared by several parents is executed once, and a node is
+/// skipped if any node below it failed. With `stop_on_failure`, no more nodes are started once any node has failed,
+/// though those already running are waited for; without it, every node that doesn't depend on a failure still runs.
+/// Steps are numbered in the order they start, out of how many the graph has.
+///
+/// Returns the exit code of `root`, or 1 if it was never executed.
+pub fn execute(
+    root: Arc<Mutex<Node>>,
+    summary: &mut BuildSummary,
+    stop_on_failure: bool,
+    jobs: &Jobs,
+) -> Result<i32, Box<dyn Error + Send + Sync>> {
+    let mut tasks = Vec::new();
+    flatten(&root, &mut tasks, &mut HashMap::new());
+
+    let mut ready = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.pending == 0)
+        .map(|(f, _)| f)
+        .collect::<VecDeque<_>>();
+    let mut root_code = 1;
+    let mut failure = None;
+    let steps = tasks
+        .iter()
+        .filter(|f| f.node.lock().unwrap().executable.is_step())
+        .count();
+    let mut started = 0;
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        let mut running = 0;
+        let mut in_use = 0;
+        let mut tokens = Vec::new();
+
+        loop {
+            let stopped = failure.is_some() || (stop_on_failure && !summary.success);
+            while let Some(&id) = ready.front().filter(|_| !stopped) {
+                let weight = tasks[id].weight;
+                if tasks[id].blocked_by.is_none() && running > 0 && !admits(jobs, &mut tokens, in_use + weight) {
+                    break;
+                }
+                ready.pop_front();
+
+                let step = tasks[id].node.lock().unwrap().executable.is_step().then(|| {
+                    started += 1;
+                    (started, steps)
+                });
+
+                if let Some(cause) = tasks[id].blocked_by.clone() {
+                    status::set_step(step);
+                    status::step(
+                        Verb::Skipped,
+                        format!(
+                            "{}, since {} failed",
+                            tasks[id].node.lock().unwrap().executable.describe(),
+                            cause
+                        ),
+                    );
+                    status::set_step(None);
+                    release(&mut tasks, &mut ready, id, Some(cause));
+                    continue;
+                }
+
+                let node = Arc::clone(&tasks[id].node);
+                let sender = sender.clone();
+                scope.spawn(move || {
+                    status::set_step(step);
+                    let description = node.lock().unwrap().executable.describe();
+                    status::log(Verbosity::Trace, Verb::Trace, format!("executing '{}'", description));
+                    let result = node.lock().unwrap().executable.execute();
+                    _ = sender.send((id, result));
+                });
+                running += 1;
+                in_use += weight;
+            }
+
+            if running == 0 {
+                if stopped {
+                    for task in ready.iter().map(|f| &tasks[*f]) {
+                        status::log(
+                            Verbosity::Trace,
+                            Verb::Trace,
+                            format!(
+                                "not executing '{}' after an earlier failure",
+                                task.node.lock().unwrap().executable.describe()
+                            ),
+                        );
+                    }
+                }
+                break;
+            }
+
+            // Tokens beyond what the running nodes need go back to the jobserver rather than wait for the next node.
+            tokens.truncate(in_use.saturating_sub(1));
+            let (id, result) = if matches!(jobs, Jobs::Jobserver(_)) && !stopped && !ready.is_empty() {
+                // Nothing says when another process gives a token back, so the jobserver is asked again every so often.
+                match receiver.recv_timeout(TOKEN_POLL_INTERVAL) {
+                    Ok(message) => message,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => panic!("a running task hung up"),
+                }
+            } else {
+                receiver.recv().expect("a running task hung up")
+            };
+            running -= 1;
+            in_use -= tasks[id].weight;
+
+            let node = tasks[id].node.lock().unwrap();
+            let code = match result {
+                Ok(code) => code,
+                Err(error) => {
+                    failure
+                        .get_or_insert_with(|| format!("failed to {}: {}", node.executable.describe(), error).into());
+                    continue;
+                },
+            };
+            status::log(
+                Verbosity::Trace,
+                Verb::Trace,
+                format!("'{}' exited with code {}", node.executable.describe(), code),
+            );
+            node.executable.summarize(code, summary);
+            summary.success &= code == 0;
+            let cause = (code != 0).then(|| node.executable.describe());
+            drop(node);
+
+            if id == 0 {
+                root_code = code;
+            }
+            release(&mut tasks, &mut ready, id, cause);
+        }
+    });
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(root_code),
+    }
+}
+
+/// Returns whether `jobs` leave room for running nodes weighing `weight` in all, taking tokens from the jobserver into
+/// `tokens` as needed.
+fn admits(jobs: &Jobs, tokens: &mut Vec<Token>, weight: usize) -> bool {
+    match jobs {
+        Jobs::Budget(budget) => weight <= budget.get(),
+        Jobs::Jobserver(client) => {
+            while 1 + tokens.len() < weight {
+                match client.try_acquire() {
+                    Some(token) => tokens.push(token),
+                    None => return false,
+                }
+            }
+            true
+        },
+    }
+}
+
+/// Marks the task `id` as finished for each of its parents, queueing those with no other children left. If `cause`
+/// names a failure the task was or depended on, the parents are blocked by it.
+fn release(tasks: &mut [Task], ready: &mut VecDeque<usize>, id: usize, cause: Option<String>) {
+    for parent in tasks[id].parents.clone() {
+        let task = &mut tasks[parent];
+        if task.blocked_by.is_none() {
+            task.blocked_by = cause.clone();
+        }
+        task.pending -= 1;
+        if task.pending == 0 {
+            ready.push_back(parent);
+        }
+    }
+}
+
+/// Adds `node` and everything below it to `tasks` once each, identifying nodes by their address. Returns the index of
+/// `node`'s task, which is 0 for the first node added.
+fn flatten(node: &Arc<Mutex<Node>>, tasks: &mut Vec<Task>, seen: &mut HashMap<*const Mutex<Node>, usize>) -> usize {
+    if let Some(&id) = seen.get(&Arc::as_ptr(node)) {
+        return id;
+    }
+
+    let id = tasks.len();
+    seen.insert(Arc::as_ptr(node), id);
+    tasks.push(Task {
+        node:       Arc::clone(node),
+        parents:    Vec::new(),
+        pending:    0,
+        blocked_by: None,
+        weight:     node.lock().unwrap().executable.weight(),
+    });
+
+    let children = node.lock().unwrap().children.clone();
+    status::log(
+        Verbosity::Trace,
+        Verb::Trace,
+        format!(
+            "visiting '{}' with {} children",
+            node.lock().unwrap().executable.describe(),
+            children.len()
+        ),
+    );
+
+    for child in &children {
+        let child = flatten(child, tasks, seen);
+        if !tasks[child].parents.contains(&id) {
+            tasks[child].parents.push(id);
+            tasks[id].pending += 1;
+        }
+    }
+
+    id
 }
@@ -0,0 +1,229 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use flate2::Compression;
+use flate2::GzBuilder;
+use itertools::Itertools;
+use sha2::Digest;
+use sha2::Sha256;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
+use zip::DateTime;
+use zip::ZipWriter;
+
+use crate::artifacts::ArtifactKind;
+use crate::artifacts::Artifacts;
+use crate::config::Project;
+use crate::config::ProjectKind;
+use crate::layout::Layout;
+use crate::storage;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ArchiveFormat {
+    #[default]
+    TarGz,
+    Zip,
+}
+
+struct Entry {
+    path:  String,
+    data:  Vec<u8>,
+    mode:  u32,
+    mtime: u64,
+}
+
+/// Packages the artifact listed in `artifacts` along with the files matched by `package.include` into
+/// `target/package/<name>-<version>-<target>.<format>`, returning the path of the archive. A library also ships the
+/// project's public headers, everything in `include`, under `include/`.
+///
+/// Every entry is rooted at `<name>-<version>/`, and a `SHA256SUMS` file listing the contents is added. When
+/// `SOURCE_DATE_EPOCH` is set, it is used as the modification time of every entry so that packaging the same sources
+/// twice produces byte-identical archives.
+pub fn package(
+    layout: &Layout,
+    project: &Project,
+    artifacts: &Artifacts,
+    format: ArchiveFormat,
+) -> Result<PathBuf, Report> {
+    let artifact = &artifacts
+        .find(ArtifactKind::of(project.package.kind))
+        .ok_or_else(|| eyre!("the last build produced nothing to package"))?
+        .path;
+    let version = project
+        .package
+        .version
+        .as_deref()
+        .ok_or_else(|| eyre!("'package.version' must be set in loki.toml to create a package"))?;
+    let root = format!("{}-{}", project.package.name, version);
+    let mtime = reproducible_mtime()?;
+
+    let name = artifact.file_name().unwrap_or_default().to_string_lossy();
+    let mut entries = vec![read_entry(artifact, format!("{}/{}", root, name), mtime)?];
+
+    if project.package.kind != ProjectKind::Binary && layout.header_directory.is_dir() {
+        for header in WalkDir::new(&layout.header_directory) {
+            let header = header?;
+            if header.file_type().is_file() {
+                let relative = header.path().strip_prefix(&layout.header_directory)?.to_string_lossy();
+                entries.push(read_entry(
+                    header.path(),
+                    format!("{}/include/{}", root, relative),
+                    mtime,
+                )?);
+            }
+        }
+    }
+
+    for pattern in &project.package.include {
+        let matches = glob::glob(&layout.project_directory.join(pattern).to_string_lossy())?;
+        for path in matches {
+            let path = path?;
+            if path.is_file() {
+                let relative = path
+                    .strip_prefix(&layout.project_directory)?
+                    .to_string_lossy()
+                    .into_owned();
+                entries.push(read_entry(&path, format!("{}/{}", root, relative), mtime)?);
+            }
+        }
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries.dedup_by(|a, b| a.path == b.path);
+
+    let sums = entries
+        .iter()
+        .map(|entry| {
+            let digest = Sha256::digest(&entry.data);
+            format!(
+                "{}  {}\n",
+                digest.iter().map(|f| format!("{:02x}", f)).join(""),
+                entry.path
+            )
+        })
+        .join("");
+    entries.push(Entry {
+        path:  format!("{}/SHA256SUMS", root),
+        data:  sums.into_bytes(),
+        mode:  0o644,
+        mtime: mtime.unwrap_or_else(|| entries.iter().map(|f| f.mtime).max().unwrap_or(0)),
+    });
+
+    fs::create_dir_all(&layout.package_directory)?;
+
+    let output = layout
+        .package_directory
+        .join(format!("{}-{}.{}", root, artifacts.target, match format {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }));
+
+    storage::write_atomically_with(&output, |f| match format {
+        ArchiveFormat::TarGz => write_tar_gz(f, &entries),
+        ArchiveFormat::Zip => write_zip(f, &entries),
+    })?;
+
+    Ok(output)
+}
+
+fn read_entry(path: &Path, archive_path: String, mtime: Option<u64>) -> Result<Entry, Report> {
+    let metadata = fs::metadata(path)?;
+
+    Ok(Entry {
+        path:  archive_path,
+        data:  fs::read(path)?,
+        mode:  metadata.permissions().mode() & 0o7777,
+        mtime: match mtime {
+            Some(mtime) => mtime,
+            None => metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs(),
+        },
+    })
+}
+
+fn write_tar_gz(file: &mut File, entries: &[Entry]) -> io::Result<()> {
+    let encoder = GzBuilder::new().mtime(0).write(file, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(entry.data.len() as u64);
+        header.set_mode(entry.mode);
+        header.set_mtime(entry.mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+        builder.append_data(&mut header, &entry.path, &entry.data[..])?;
+    }
+
+    builder.into_inner()?.finish()?.flush()?;
+
+    Ok(())
+}
+
+fn write_zip(file: &mut File, entries: &[Entry]) -> io::Result<()> {
+    let mut writer = ZipWriter::new(file);
+
+    for entry in entries {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(entry.mode)
+            .last_modified_time(zip_date_time(entry.mtime));
+
+        writer.start_file(&entry.path, options)?;
+        writer.write_all(&entry.data)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Reads `SOURCE_DATE_EPOCH`, the conventional way of requesting reproducible timestamps.
+fn reproducible_mtime() -> Result<Option<u64>, Report> {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .map(|f| {
+            f.parse()
+                .map_err(|_| eyre!("SOURCE_DATE_EPOCH must be a number of seconds, found '{}'", f))
+        })
+        .transpose()
+}
+
+/// Converts seconds since the Unix epoch to a ZIP timestamp, clamping to the range ZIP can represent.
+fn zip_date_time(seconds: u64) -> DateTime {
+    // Days-to-civil conversion from Howard Hinnant's date algorithms.
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u8;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u8;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let time = seconds % 86400;
+
+    DateTime::from_date_and_time(
+        year.clamp(1980, 2107) as u16,
+        month,
+        day,
+        (time / 3600) as u8,
+        (time % 3600 / 60) as u8,
+        (time % 60) as u8,
+    )
+    .unwrap_or_default()
+}
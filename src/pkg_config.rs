@@ -0,0 +1,92 @@
+use std::env;
+use std::io;
+use std::process::Command;
+use std::process::Output;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+/// The flags pkg-config gives for building against a set of packages.
+#[derive(Clone, Debug, Default)]
+pub struct Packages {
+    /// Flags for compiling, such as `-I` and `-D`.
+    pub cflags: Vec<String>,
+    /// Flags for linking, such as `-L` and `-l`, in the order static linking needs.
+    pub libs:   Vec<String>,
+}
+
+/// Looks up the flags of `packages` with pkg-config, or the program `$PKG_CONFIG` names. Nothing is run when there
+/// are no packages, so pkg-config is only needed by projects that use it.
+pub fn query(packages: &[String]) -> Result<Packages, Report> {
+    if packages.is_empty() {
+        return Ok(Packages::default());
+    }
+
+    let program = env::var("PKG_CONFIG").unwrap_or_else(|_| "pkg-config".to_owned());
+
+    // Checked one at a time, since pkg-config's own message for a missing package depends on its version.
+    for package in packages {
+        if !run(&program, &["--exists", package])?.status.success() {
+            return Err(eyre!(
+                "pkg-config can't find the package '{}' listed in 'pkg-config'; is its development package installed?",
+                package
+            ));
+        }
+    }
+
+    Ok(Packages {
+        cflags: flags(&program, "--cflags", packages)?,
+        libs:   flags(&program, "--libs", packages)?,
+    })
+}
+
+fn run(program: &str, args: &[&str]) -> Result<Output, Report> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => eyre!(
+                "'{}' is not installed, but is needed to look up the packages listed in 'pkg-config'",
+                program
+            ),
+            _ => eyre!("couldn't run '{}': {}", program, error),
+        })
+}
+
+/// Runs pkg-config with `mode` for all of `packages` at once, so flags they share are given once and in order.
+fn flags(program: &str, mode: &str, packages: &[String]) -> Result<Vec<String>, Report> {
+    let args = [&[mode][..], &packages.iter().map(String::as_str).collect::<Vec<_>>()].concat();
+    let output = run(program, &args)?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "'{} {}' failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(split(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Splits pkg-config's output into arguments, where a backslash escapes the character after it, as in a shell.
+fn split(output: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut current = String::new();
+    let mut characters = output.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => current.extend(characters.next()),
+            character if character.is_whitespace() =>
+                if !current.is_empty() {
+                    flags.push(std::mem::take(&mut current));
+                },
+            character => current.push(character),
+        }
+    }
+    if !current.is_empty() {
+        flags.push(current);
+    }
+
+    flags
+}
@@ -0,0 +1,206 @@
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::UNIX_EPOCH;
+
+use color_eyre::Report;
+use serde::Deserialize;
+use serde::Serialize;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::status;
+use crate::status::Verb;
+use crate::status::Verbosity;
+use crate::storage;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ProbeCache {
+    /// Fingerprint of the compiler the results were obtained with.
+    compiler: String,
+    results:  BTreeMap<String, bool>,
+}
+
+/// Identifies a compiler with [`fingerprint`], which is only run the first time the fingerprint is asked for, so
+/// every part of a build that needs it shares a single `--version`.
+pub struct Fingerprint {
+    compiler:    String,
+    fingerprint: OnceCell<String>,
+}
+
+impl Fingerprint {
+    pub fn of(compiler: &str) -> Self {
+        Self {
+            compiler:    compiler.to_owned(),
+            fingerprint: OnceCell::new(),
+        }
+    }
+
+    pub fn compiler(&self) -> &str {
+        &self.compiler
+    }
+
+    pub fn get(&self) -> Result<&str, Report> {
+        if let Some(fingerprint) = self.fingerprint.get() {
+            return Ok(fingerprint);
+        }
+
+        let fingerprint = fingerprint(&self.compiler)?;
+        Ok(self.fingerprint.get_or_init(|| fingerprint))
+    }
+}
+
+/// Lazily tests whether the compiler accepts individual flags, caching the results per compiler in
+/// `probes.json` in the state directory.
+pub struct Probes<'a> {
+    compiler: &'a Fingerprint,
+    path:     PathBuf,
+    /// The cached results, read the first time a flag is asked about.
+    cache:    Option<ProbeCache>,
+    enabled:  bool,
+    dirty:    bool,
+}
+
+impl<'a> Probes<'a> {
+    /// Prepares probing `compiler`. Nothing is read, and the compiler isn't run, until a flag is asked about. When
+    /// `enabled` is false, no probes are run and every flag is assumed to be unsupported.
+    pub fn load(compiler: &'a Fingerprint, state_directory: &Path, enabled: bool) -> Self {
+        Self {
+            compiler,
+            path: state_directory.join("probes.json"),
+            cache: None,
+            enabled,
+            dirty: false,
+        }
+    }
+
+    /// Reads the cached probe results. Results recorded for a different compiler are discarded.
+    fn cache(&mut self) -> Result<&mut ProbeCache, Report> {
+        if self.cache.is_none() {
+            let fingerprint = self.compiler.get()?;
+            let cache = fs::read(&self.path)
+                .ok()
+                .and_then(|f| match serde_json::from_slice::<ProbeCache>(&f) {
+                    Ok(cache) => Some(cache),
+                    Err(_) => {
+                        status::status(
+                            Verb::Warning,
+                            format!("discarding corrupt {}; probes will be rerun", self.path.display()),
+                        );
+                        None
+                    },
+                })
+                .filter(|f| f.compiler == fingerprint)
+                .unwrap_or_else(|| ProbeCache {
+                    compiler: fingerprint.to_owned(),
+                    results:  BTreeMap::new(),
+                });
+            self.cache = Some(cache);
+        }
+
+        Ok(self.cache.as_mut().unwrap())
+    }
+
+    /// Returns whether the compiler accepts `flag`, compiling a trivial translation unit with `-Werror <flag>` the
+    /// first time a flag is asked about.
+    pub fn supports(&mut self, flag: &str) -> Result<bool, Report> {
+        if !self.enabled {
+            status::log(
+                Verbosity::Decisions,
+                Verb::Probed,
+                format!("{}: assumed unsupported (probes disabled)", flag),
+            );
+            return Ok(false);
+        }
+
+        if let Some(&supported) = self.cache()?.results.get(flag) {
+            status::log(
+                Verbosity::Decisions,
+                Verb::Probed,
+                format!(
+                    "{}: {} (cached)",
+                    flag,
+                    if supported { "supported" } else { "unsupported" }
+                ),
+            );
+            return Ok(supported);
+        }
+
+        let directory = self.path.parent().unwrap();
+        fs::create_dir_all(directory)?;
+
+        let mut child = Command::new(self.compiler.compiler())
+            .arg("-Werror")
+            .arg(flag)
+            .args(["-x", "c", "-c", "-o"])
+            .arg(directory.join("probe.o"))
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"int main(void) { return 0; }\n")?;
+        let supported = child.wait()?.success();
+        _ = fs::remove_file(directory.join("probe.o"));
+
+        status::status(
+            Verb::Probed,
+            format!("{}: {}", flag, if supported { "supported" } else { "unsupported" }),
+        );
+
+        self.cache()?.results.insert(flag.to_owned(), supported);
+        self.dirty = true;
+
+        Ok(supported)
+    }
+
+    /// Writes the cache back to disk if any new probes were run.
+    pub fn save(&self) -> Result<(), Report> {
+        if let Some(cache) = self.cache.as_ref().filter(|_| self.dirty) {
+            fs::create_dir_all(self.path.parent().unwrap())?;
+            storage::write_atomically(&self.path, &serde_json::to_vec_pretty(cache)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Identifies a compiler by its name, resolved path, modification time and `--version` output, so upgrading the
+/// compiler invalidates old results even when the version string stays the same.
+pub fn fingerprint(compiler: &str) -> Result<String, Report> {
+    let output = Command::new(compiler).arg("--version").output()?;
+
+    let mut identity = output.stdout;
+    if let Some(path) = resolve(compiler) {
+        identity.extend_from_slice(path.as_os_str().as_encoded_bytes());
+        let modified = fs::metadata(&path)
+            .and_then(|f| f.modified())
+            .ok()
+            .and_then(|f| f.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |f| f.as_nanos());
+        identity.extend_from_slice(&modified.to_le_bytes());
+    }
+
+    Ok(format!("{}-{:x}", compiler, xxh3_64(&identity)))
+}
+
+/// Finds the file `program` runs, following symlinks, the way the shell searches `PATH`.
+fn resolve(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return fs::canonicalize(program).ok();
+    }
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|f| f.join(program))
+        .find(|f| f.is_file())
+        .and_then(|f| fs::canonicalize(f).ok())
+}
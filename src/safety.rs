@@ -0,0 +1,125 @@
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+use crate::status;
+use crate::status::Verb;
+
+/// Directories that hold the system or every user's files, which are never project roots. macOS resolves `/etc`,
+/// `/tmp` and `/var` to their counterparts in `/private`.
+const SYSTEM_DIRECTORIES: &[&str] = &[
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/home",
+    "/lib",
+    "/lib64",
+    "/opt",
+    "/proc",
+    "/root",
+    "/sbin",
+    "/sys",
+    "/tmp",
+    "/usr",
+    "/usr/local",
+    "/var",
+    "/Applications",
+    "/Library",
+    "/System",
+    "/Users",
+    "/private",
+    "/private/etc",
+    "/private/tmp",
+    "/private/var",
+];
+
+/// Refuses directories that are almost certainly not meant to be project roots, such as `/` or the home directory,
+/// where a stray `loki.toml` would otherwise lead Loki to walk and compile a large part of the filesystem.
+pub fn check_project_directory(project_directory: &Path, allow_root_project: bool) -> Result<(), Report> {
+    if allow_root_project {
+        return Ok(());
+    }
+
+    let project_directory = project_directory.canonicalize()?;
+    let home = env::var_os("HOME").and_then(|f| PathBuf::from(f).canonicalize().ok());
+
+    match refusal(&project_directory, home.as_deref()) {
+        Some(reason) => Err(eyre!(
+            "refusing to use {} as a project directory because {}; if this is intended, pass --allow-root-project",
+            project_directory.display(),
+            reason
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Why the canonical `project_directory` can't be a project root, given the canonical home directory.
+fn refusal(project_directory: &Path, home: Option<&Path>) -> Option<&'static str> {
+    if project_directory.parent().is_none() {
+        Some("it is the root of the filesystem")
+    } else if SYSTEM_DIRECTORIES.iter().any(|f| project_directory == Path::new(f)) {
+        Some("it is a system directory")
+    } else if home == Some(project_directory) {
+        Some("it is your home directory")
+    } else {
+        None
+    }
+}
+
+/// Warns when running as root outside of a container or CI, where a mistake can damage the system.
+pub fn warn_if_superuser() {
+    #[cfg(unix)]
+    {
+        // SAFETY: `geteuid` has no preconditions and cannot fail.
+        let superuser = unsafe { libc::geteuid() } == 0;
+        let sandboxed = env::var_os("CI").is_some()
+            || Path::new("/.dockerenv").exists()
+            || Path::new("/run/.containerenv").exists();
+
+        if superuser && !sandboxed {
+            status::status(
+                Verb::Warning,
+                "running as root; build steps can modify any file on the system",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks `directory` with `/home/user` as the home directory.
+    fn refused(directory: &str) -> Option<&'static str> {
+        refusal(Path::new(directory), Some(Path::new("/home/user")))
+    }
+
+    #[test]
+    fn root_is_refused() {
+        assert_eq!(refused("/"), Some("it is the root of the filesystem"));
+    }
+
+    #[test]
+    fn system_directories_are_refused() {
+        for directory in ["/usr", "/etc", "/home", "/usr/local", "/private/var", "/Users"] {
+            assert_eq!(refused(directory), Some("it is a system directory"), "{}", directory);
+        }
+    }
+
+    #[test]
+    fn home_directory_is_refused() {
+        assert_eq!(refused("/home/user"), Some("it is your home directory"));
+        assert_eq!(refusal(Path::new("/home/user"), None), None);
+    }
+
+    #[test]
+    fn top_level_project_directories_are_allowed() {
+        for directory in ["/app", "/src", "/workspace", "/home/user/project", "/usr/src/project"] {
+            assert_eq!(refused(directory), None, "{}", directory);
+        }
+    }
+}
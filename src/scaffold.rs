@@ -0,0 +1,156 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+use crate::config::Project;
+use crate::layout::Layout;
+use crate::status;
+use crate::status::Verb;
+
+/// Creates a project for a binary called `name` in `directory`: a `loki.toml` pinning the current defaults and a
+/// hello-world `main.c`, in `src` or, with `flat`, next to the manifest. Neither file may exist yet.
+pub fn new_project(directory: &Path, name: &str, flat: bool) -> Result<(), Report> {
+    let valid = name.chars().next().is_some_and(|f| f.is_ascii_alphabetic() || f == '_')
+        && name.chars().all(|f| f.is_ascii_alphanumeric() || f == '_' || f == '-');
+    if !valid {
+        return Err(eyre!(
+            "'{}' is not a valid project name; use letters, digits, '_' and '-', starting with a letter or '_'",
+            name
+        ));
+    }
+
+    let manifest_path = directory.join("loki.toml");
+    let source = match flat {
+        true => directory.join("main.c"),
+        false => directory.join("src/main.c"),
+    };
+    for path in [&manifest_path, &source] {
+        if path.exists() {
+            return Err(eyre!("{} already exists", path.display()));
+        }
+    }
+
+    let mut manifest = format!(
+        "[package]\nname = \"{}\"\ntype = \"binary\"\nversion = \"0.1.0\"\ndefaults = \"2025\"\n",
+        name
+    );
+    if flat {
+        manifest.push_str("source-dir = \".\"\n");
+    }
+    manifest.push_str("\n[configuration]\nc-standard = \"c17\"\nopt-level = 2\n");
+    // Loading the manifest the way builds do catches a template that has drifted from the manifest format.
+    toml::from_str::<Project>(&manifest)?;
+
+    write(&manifest_path, &manifest)?;
+    write(
+        &source,
+        "#include <stdio.h>\n\nint main(void) {\n    puts(\"Hello, world!\");\n    return 0;\n}\n",
+    )?;
+
+    Ok(())
+}
+
+/// Creates `<name>.c` in `source_directory`, and with `header` also `include/<name>.h`, from a template. Existing
+/// files are only overwritten with `force`.
+pub fn add_file(layout: &Layout, source_directory: &Path, name: &str, header: bool, force: bool) -> Result<(), Report> {
+    let (source, header_path) = paths(layout, source_directory, name)?;
+    let header_path = header.then_some(header_path);
+
+    for path in [Some(&source), header_path.as_ref()].into_iter().flatten() {
+        if path.exists() && !force {
+            return Err(eyre!("{} already exists; pass --force to overwrite it", path.display()));
+        }
+    }
+
+    let stem = Path::new(name).file_name().unwrap_or_default().to_string_lossy();
+
+    if let Some(header_path) = &header_path {
+        let guard = format!(
+            "{}_H",
+            name.chars()
+                .map(|f| if f.is_ascii_alphanumeric() {
+                    f.to_ascii_uppercase()
+                } else {
+                    '_'
+                })
+                .collect::<String>()
+        );
+        write(
+            header_path,
+            &format!("#ifndef {guard}\n#define {guard}\n\n#endif /* {guard} */\n"),
+        )?;
+    }
+
+    write(&source, &match header_path {
+        Some(_) => format!("#include \"{}.h\"\n", name),
+        None => format!("/* {}.c */\n", stem),
+    })?;
+
+    Ok(())
+}
+
+/// Deletes the files created by [`add_file`] for `name`, asking for confirmation first unless `yes` is set.
+pub fn remove_file(layout: &Layout, source_directory: &Path, name: &str, yes: bool) -> Result<(), Report> {
+    let (source, header) = paths(layout, source_directory, name)?;
+    let existing = [source, header].into_iter().filter(|f| f.exists()).collect::<Vec<_>>();
+
+    if existing.is_empty() {
+        return Err(eyre!("no source or header named '{}' exists", name));
+    }
+
+    if !yes {
+        eprint!(
+            "Remove {}? [y/N] ",
+            existing
+                .iter()
+                .map(|f| f.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" and ")
+        );
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            status::status(Verb::Skipped, "removing files");
+            return Ok(());
+        }
+    }
+
+    for path in existing {
+        fs::remove_file(&path)?;
+        status::status(Verb::Removed, path.display());
+    }
+
+    Ok(())
+}
+
+/// Returns the source and header paths for `name`, which may contain directories but must stay inside the project.
+fn paths(layout: &Layout, source_directory: &Path, name: &str) -> Result<(PathBuf, PathBuf), Report> {
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|f| matches!(f, Component::Normal(_))) {
+        return Err(eyre!(
+            "'{}' is not a valid file name; give a name relative to the source directory",
+            name
+        ));
+    }
+
+    Ok((
+        source_directory.join(format!("{}.c", name)),
+        layout.header_directory.join(format!("{}.h", name)),
+    ))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), Report> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(path, contents)?;
+    status::status(Verb::Created, path.display());
+
+    Ok(())
+}
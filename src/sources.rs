@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use walkdir::WalkDir;
+
+use crate::compiler::Language;
+use crate::config::Package;
+use crate::layout::Layout;
+
+/// A source that is part of the build, along with why.
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub path:   PathBuf,
+    pub reason: Reason,
+}
+
+#[derive(Clone, Debug)]
+pub enum Reason {
+    /// Listed in `package.sources`.
+    Listed,
+    /// Found while searching the given source directory.
+    Discovered(PathBuf),
+}
+
+/// Resolves the sources of `package`: those listed in `sources` if it is set, and those found in the source
+/// directories if it isn't or if `discover-sources` is also set.
+pub fn sources(layout: &Layout, package: &Package, max_source_files: usize) -> Result<Vec<Source>, Report> {
+    let mut sources = match &package.sources {
+        Some(listed) => listed_sources(layout, listed)?,
+        None => Vec::new(),
+    };
+
+    if package.sources.is_none() || package.discover_sources {
+        let discovered = discover_sources(layout, &layout.source_directories(package)?, max_source_files)?;
+        for source in discovered {
+            if !sources.iter().any(|f| f.path == source.path) {
+                sources.push(source);
+            }
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Resolves the sources listed in the manifest, rejecting any that don't exist or aren't C or C++ sources.
+fn listed_sources(layout: &Layout, listed: &[PathBuf]) -> Result<Vec<Source>, Report> {
+    let mut sources = Vec::new();
+
+    for source in listed {
+        let path: PathBuf = layout.project_directory.join(source).components().collect();
+        if !path.is_file() {
+            return Err(eyre!("listed source '{}' does not exist", source.display()));
+        }
+        if Language::of(&path).is_none() {
+            return Err(eyre!("listed source '{}' is not a C or C++ source", source.display()));
+        }
+        if !sources.iter().any(|f: &Source| f.path == path) {
+            sources.push(Source {
+                path,
+                reason: Reason::Listed,
+            });
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Walks the source directories for C and C++ sources. The `target` and test directories are never walked, even when
+/// they are inside a source directory, so emitted files of any build are never compiled and tests never become part
+/// of the project.
+fn discover_sources(layout: &Layout, directories: &[PathBuf], max_source_files: usize) -> Result<Vec<Source>, Report> {
+    let mut sources = Vec::new();
+    let mut count = 0;
+
+    for directory in directories {
+        let entries = WalkDir::new(directory)
+            .into_iter()
+            .filter_entry(|f| f.path() != layout.build_directory && f.path() != layout.test_directory);
+
+        for entry in entries {
+            count += 1;
+            if count > max_source_files {
+                return Err(eyre!(
+                    "found more than {} files under {}; the project root is probably wrong (raise 'max-source-files' \
+                     if it is not)",
+                    max_source_files,
+                    directory.display()
+                ));
+            }
+
+            let path = entry
+                .map_err(|f| {
+                    eyre!(
+                        "can't read {} while looking for sources: {}",
+                        f.path().unwrap_or(directory).display(),
+                        f.io_error().map_or_else(|| f.to_string(), |f| f.to_string())
+                    )
+                })?
+                .into_path();
+            if Language::of(&path).is_some() {
+                sources.push(Source {
+                    path,
+                    reason: Reason::Discovered(directory.clone()),
+                });
+            }
+        }
+    }
+
+    Ok(sources)
+}
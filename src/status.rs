@@ -0,0 +1,180 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::io;
+use std::io::IsTerminal;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+use owo_colors::OwoColorize;
+
+use crate::config::Color;
+
+static COLOR: AtomicU8 = AtomicU8::new(Color::Auto as u8);
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+thread_local! {
+    /// The numbered step of the build graph the thread is executing, and how many steps there are.
+    static STEP: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// How much of Loki's own output is shown. Each level includes everything shown by the levels below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only warnings and errors.
+    Quiet,
+    /// What is being built, and the outcome.
+    #[default]
+    Normal,
+    /// Every command that is run, and the full output of tools even when Loki summarizes it.
+    Commands,
+    /// Why each source is or isn't rebuilt, and which cached results are used.
+    Decisions,
+    /// The order in which the build graph is executed.
+    Trace,
+}
+
+impl Verbosity {
+    /// Returns the level selected by `count` stacked `-v` flags.
+    pub fn from_count(count: usize) -> Self {
+        match count {
+            0 => Verbosity::Normal,
+            1 => Verbosity::Commands,
+            2 => Verbosity::Decisions,
+            _ => Verbosity::Trace,
+        }
+    }
+}
+
+/// The word at the start of a status line, describing what is happening.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verb {
+    Compiling,
+    Fresh,
+    Linking,
+    Archiving,
+    Skipped,
+    Running,
+    Probed,
+    Checking,
+    Trace,
+    Generated,
+    Created,
+    Emitted,
+    Packaged,
+    Removed,
+    Cached,
+    Updated,
+    Finished,
+    Passed,
+    Changed,
+    Warning,
+    Failed,
+    Error,
+}
+
+impl Verb {
+    fn as_str(self) -> &'static str {
+        match self {
+            Verb::Compiling => "Compiling",
+            Verb::Fresh => "Fresh",
+            Verb::Linking => "Linking",
+            Verb::Archiving => "Archiving",
+            Verb::Skipped => "Skipped",
+            Verb::Running => "Running",
+            Verb::Probed => "Probed",
+            Verb::Checking => "Checking",
+            Verb::Trace => "Trace",
+            Verb::Generated => "Generated",
+            Verb::Created => "Created",
+            Verb::Emitted => "Emitted",
+            Verb::Packaged => "Packaged",
+            Verb::Removed => "Removed",
+            Verb::Cached => "Cached",
+            Verb::Updated => "Updated",
+            Verb::Finished => "Finished",
+            Verb::Passed => "Passed",
+            Verb::Changed => "Changed",
+            Verb::Warning => "Warning",
+            Verb::Failed => "Failed",
+            Verb::Error => "Error",
+        }
+    }
+}
+
+/// Sets how Loki's own messages, as opposed to the output of the tools it runs, are shown. Every such message goes
+/// through this module.
+pub fn configure(color: Color, verbosity: Verbosity) {
+    COLOR.store(color as u8, Ordering::Relaxed);
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        level if level == Verbosity::Quiet as u8 => Verbosity::Quiet,
+        level if level == Verbosity::Commands as u8 => Verbosity::Commands,
+        level if level == Verbosity::Decisions as u8 => Verbosity::Decisions,
+        level if level == Verbosity::Trace as u8 => Verbosity::Trace,
+        _ => Verbosity::Normal,
+    }
+}
+
+/// Prints a status line to standard error. Warnings and errors are shown even when quiet.
+pub fn status(verb: Verb, message: impl Display) {
+    if verbosity() == Verbosity::Quiet && !matches!(verb, Verb::Warning | Verb::Failed | Verb::Error) {
+        return;
+    }
+
+    let color = match COLOR.load(Ordering::Relaxed) {
+        color if color == Color::Always as u8 => true,
+        color if color == Color::Never as u8 => false,
+        _ => io::stderr().is_terminal(),
+    };
+
+    eprintln!("{}", render(verb, message, color));
+}
+
+/// Sets the numbered step of how many that the calling thread executes, for the lines it prints with [`step`].
+pub fn set_step(step: Option<(usize, usize)>) {
+    STEP.with(|f| f.set(step));
+}
+
+/// Prints the status line announcing a step of the build graph. While the graph is executed, the message starts with
+/// which step of how many it is, as in `Compiling [ 3/17] src/main.c`; the verb stays in its column so the line lines
+/// up with every other status line.
+pub fn step(verb: Verb, message: impl Display) {
+    match STEP.with(Cell::get) {
+        Some((number, total)) => status(
+            verb,
+            format!(
+                "[{:>width$}/{}] {}",
+                number,
+                total,
+                message,
+                width = total.to_string().len()
+            ),
+        ),
+        None => status(verb, message),
+    }
+}
+
+/// Prints a status line only when at least `level` of verbosity was requested.
+pub fn log(level: Verbosity, verb: Verb, message: impl Display) {
+    if verbosity() >= level {
+        status(verb, message);
+    }
+}
+
+/// Formats a status line with the verb right-aligned in a fixed-width column, so the messages line up.
+pub fn render(verb: Verb, message: impl Display, color: bool) -> String {
+    let padded = format!("{:>12}", verb.as_str());
+
+    if !color {
+        return format!("{} {}", padded, message);
+    }
+
+    match verb {
+        Verb::Warning => format!("{} {}", padded.yellow().bold(), message),
+        Verb::Failed | Verb::Error => format!("{} {}", padded.red().bold(), message),
+        _ => format!("{} {}", padded.green().bold(), message),
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+/// Adds the path being written to an I/O error. Running out of space is called out explicitly, since otherwise it
+/// tends to surface later as a confusing failure to read back what was written.
+pub fn writing(path: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |error| match error.kind() {
+        io::ErrorKind::StorageFull => io::Error::new(
+            error.kind(),
+            format!("no space left on device while writing {}", path.display()),
+        ),
+        _ => io::Error::new(error.kind(), format!("failed to write {}: {}", path.display(), error)),
+    }
+}
+
+/// Writes `contents` to `path` by way of a temporary file, so readers never see a partially written file.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    write_atomically_with(path, |f| f.write_all(contents))
+}
+
+/// Writes `path` with `write` by way of a temporary file, which is removed again if anything fails, so `path` is
+/// either replaced as a whole or left as it was.
+pub fn write_atomically_with(path: &Path, write: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    let result = File::create(&temporary)
+        .and_then(|mut f| write(&mut f))
+        .and_then(|_| fs::rename(&temporary, path));
+    if result.is_err() {
+        _ = fs::remove_file(&temporary);
+    }
+
+    result.map_err(writing(path))
+}
+
+/// Returns a path next to `path` that no other write in any running build uses, for a tool to write into before the
+/// result is renamed into place.
+pub fn temporary_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(
+        ".tmp-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_the_contents() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("state");
+        fs::write(&path, "old").unwrap();
+
+        write_atomically(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn failure_after_the_temporary_exists_keeps_the_original() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("state");
+        fs::write(&path, "old").unwrap();
+
+        let error = write_atomically_with(&path, |f| {
+            f.write_all(b"partial")?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("no space left on device while writing"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn temporaries_are_unique() {
+        let path = Path::new("target/debug/artifacts.json");
+        assert_ne!(temporary_path(path), temporary_path(path));
+        assert_eq!(temporary_path(path).parent(), path.parent());
+    }
+}
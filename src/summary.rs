@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::status;
+use crate::status::Verb;
+use crate::status::Verbosity;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildSummary {
+    pub success:         bool,
+    pub compiled:        usize,
+    pub skipped:         usize,
+    pub relinked:        usize,
+    pub warnings:        usize,
+    pub errors:          usize,
+    pub elapsed_seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package:         Option<PathBuf>,
+    /// Files emitted in place of objects, such as assembly.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub emitted:         Vec<PathBuf>,
+    /// Sources that produced warnings, with how many each.
+    #[serde(skip)]
+    pub warning_sources: Vec<(PathBuf, usize)>,
+    /// What failed, in the order it finished, such as `failed to compile src/main.c (clang exited with status 1)`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures:        Vec<String>,
+}
+
+impl Default for BuildSummary {
+    fn default() -> Self {
+        Self {
+            success:         true,
+            compiled:        0,
+            skipped:         0,
+            relinked:        0,
+            warnings:        0,
+            errors:          0,
+            elapsed_seconds: 0.0,
+            package:         None,
+            emitted:         Vec::new(),
+            warning_sources: Vec::new(),
+            failures:        Vec::new(),
+        }
+    }
+}
+
+impl BuildSummary {
+    /// Records that `description`, run with `program`, exited with the nonzero `code`.
+    pub fn fail(&mut self, description: &str, program: &str, code: i32) {
+        self.failures.push(format!(
+            "failed to {} ({} exited with status {})",
+            description, program, code
+        ));
+    }
+
+    pub fn finish(&mut self, elapsed: Duration) {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+    }
+
+    /// Fails the build if it produced more than `budget` warnings, listing the sources with the most.
+    pub fn check_warning_budget(&mut self, budget: usize) {
+        if self.warnings <= budget {
+            return;
+        }
+
+        self.success = false;
+        status::status(
+            Verb::Failed,
+            format!(
+                "{} warnings exceed the budget of {} set by 'max-warnings'",
+                self.warnings, budget
+            ),
+        );
+
+        let mut sources = self.warning_sources.clone();
+        sources.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (source, warnings) in sources.iter().take(5) {
+            status::log(
+                Verbosity::Normal,
+                Verb::Warning,
+                format!("{}: {}", source.display(), warnings),
+            );
+        }
+    }
+
+    pub fn emit(&self, format: OutputFormat) -> Result<(), serde_json::Error> {
+        match format {
+            OutputFormat::Human => {
+                status::status(
+                    if self.success { Verb::Finished } else { Verb::Failed },
+                    format!(
+                        "build in {:.2}s: {} compiled, {} skipped, {} relinked, {} warnings, {} errors",
+                        self.elapsed_seconds, self.compiled, self.skipped, self.relinked, self.warnings, self.errors,
+                    ),
+                );
+
+                for failure in &self.failures {
+                    eprintln!("{:>12} {}", "", failure);
+                }
+
+                for emitted in &self.emitted {
+                    status::status(Verb::Emitted, emitted.display());
+                }
+
+                if let Some(package) = &self.package {
+                    status::status(Verb::Packaged, package.display());
+                }
+            },
+            OutputFormat::Json => println!("{}", serde_json::to_string(self)?),
+        }
+
+        Ok(())
+    }
+}
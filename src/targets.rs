@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::artifacts::ArtifactKind;
+use crate::config::Project;
+use crate::layout::Layout;
+use crate::summary::OutputFormat;
+
+/// Something building the project produces, as listed by `loki list`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Target {
+    pub name:    String,
+    pub kind:    ArtifactKind,
+    /// Where the target is written, whether or not it has been built yet.
+    pub path:    PathBuf,
+    /// Whether `loki build` builds the target when no target is named.
+    pub default: bool,
+}
+
+/// Resolves the targets of `project` without building anything.
+pub fn targets(layout: &Layout, project: &Project) -> Vec<Target> {
+    vec![Target {
+        name:    project.package.name.clone(),
+        kind:    ArtifactKind::of(project.package.kind),
+        path:    layout.artifact(&project.package),
+        default: true,
+    }]
+}
+
+pub fn print(layout: &Layout, targets: &[Target], format: OutputFormat) -> Result<(), serde_json::Error> {
+    match format {
+        OutputFormat::Human =>
+            for target in targets {
+                println!(
+                    "{} ({}) {}{}",
+                    target.name,
+                    target.kind.name(),
+                    target
+                        .path
+                        .strip_prefix(&layout.project_directory)
+                        .unwrap_or(&target.path)
+                        .display(),
+                    if target.default { " [default]" } else { "" }
+                );
+            },
+        OutputFormat::Json => println!("{}", serde_json::to_string(targets)?),
+    }
+
+    Ok(())
+}
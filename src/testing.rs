@@ -0,0 +1,104 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+use crate::compiler::Language;
+use crate::executable::display_command;
+use crate::layout::Layout;
+use crate::status;
+use crate::status::Verb;
+use crate::status::Verbosity;
+
+/// A source directly in `tests/`, which is linked with the project's objects into a binary of its own. The test
+/// passes if the binary exits with status 0.
+#[derive(Clone, Debug)]
+pub struct Test {
+    /// The file stem of the source, which names the binary and selects the test with `loki test <name>`.
+    pub name:   String,
+    pub source: PathBuf,
+    pub binary: PathBuf,
+}
+
+/// Finds the tests of the project at `layout`, sorted by name. A missing `tests/` directory has no tests.
+pub fn discover(layout: &Layout) -> Result<Vec<Test>, Report> {
+    let entries = match fs::read_dir(&layout.test_directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(eyre!("can't read {}: {}", layout.test_directory.display(), error)),
+    };
+
+    let mut tests: Vec<Test> = Vec::new();
+    for entry in entries {
+        let source = entry?.path();
+        if !source.is_file() || Language::of(&source).is_none() {
+            continue;
+        }
+
+        let name = source.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        if let Some(other) = tests.iter().find(|f| f.name == name) {
+            return Err(eyre!(
+                "{} and {} would both build the test '{}'",
+                other.source.display(),
+                source.display(),
+                name
+            ));
+        }
+        tests.push(Test {
+            binary: layout.test_binaries.join(&name),
+            name,
+            source,
+        });
+    }
+
+    tests.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tests)
+}
+
+/// Runs each test from `project_directory`, capturing its output and showing it only if the test fails. Returns how
+/// many tests failed.
+pub fn run(tests: &[Test], project_directory: &Path) -> Result<usize, Report> {
+    let mut failed = 0;
+
+    for test in tests {
+        let mut command = Command::new(&test.binary);
+        command.current_dir(project_directory);
+        status::log(Verbosity::Commands, Verb::Running, display_command(&command));
+
+        let output = command
+            .output()
+            .map_err(|f| eyre!("couldn't run the test '{}': {}", test.name, f))?;
+        if output.status.success() {
+            status::status(Verb::Passed, &test.name);
+            continue;
+        }
+
+        failed += 1;
+        status::status(Verb::Failed, format!("{} ({})", test.name, outcome(output.status)));
+        io::stderr().write_all(&output.stdout)?;
+        io::stderr().write_all(&output.stderr)?;
+    }
+
+    status::status(
+        if failed == 0 { Verb::Finished } else { Verb::Failed },
+        format!("tests: {} passed, {} failed", tests.len() - failed, failed),
+    );
+
+    Ok(failed)
+}
+
+/// Describes how a failed test ended.
+fn outcome(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with status {}", code),
+        (None, Some(signal)) => format!("killed by signal {}", signal),
+        (None, None) => status.to_string(),
+    }
+}
@@ -0,0 +1,88 @@
+use std::env;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+use crate::config::EffectiveSettings;
+use crate::config::Origin;
+use crate::config::Setting;
+use crate::config::TargetSettings;
+
+/// Switches `settings` to the toolchain for cross-compiling to `triple`, as `target`, the `[target.<triple>]` section
+/// of the manifest, configures it. Tools it doesn't set are the GNU ones prefixed with the triple. The compilers are
+/// replaced rather than layered, since a host compiler given with `--cc` or `$CC` would build for the wrong target,
+/// while the target's flags are passed after the project's own.
+///
+/// Fails if no compiler is configured and `<triple>-gcc` isn't on `PATH`, rather than building for the host.
+pub fn cross(
+    settings: &mut EffectiveSettings,
+    triple: &str,
+    target: Option<&TargetSettings>,
+    project_directory: &Path,
+) -> Result<(), Report> {
+    let configured = target.cloned().unwrap_or_default();
+    let tool = |configured: Option<String>, name: &str| match configured {
+        Some(value) => Setting {
+            value,
+            origin: Origin::Manifest,
+        },
+        None => Setting {
+            value:  format!("{}-{}", triple, name),
+            origin: Origin::Target,
+        },
+    };
+
+    if configured.cc.is_none() && !on_path(&format!("{}-gcc", triple)) {
+        return Err(match target {
+            Some(_) => eyre!(
+                "'[target.{0}]' in loki.toml doesn't set 'cc', and '{0}-gcc' isn't on PATH; set 'cc' to the compiler \
+                 for {0}",
+                triple
+            ),
+            None => eyre!(
+                "no toolchain for the target '{0}': add a '[target.{0}]' section to loki.toml with 'cc' set to the \
+                 compiler for {0}, or put '{0}-gcc' on PATH",
+                triple
+            ),
+        });
+    }
+
+    settings.cc = tool(configured.cc, "gcc");
+    settings.cxx = tool(configured.cxx, "g++");
+    settings.ar = tool(configured.ar, "ar");
+    // A GNU toolchain usually comes without lld, so it links with its own linker unless told otherwise.
+    settings.linker = Setting {
+        origin: match configured.linker {
+            Some(_) => Origin::Manifest,
+            None => Origin::Target,
+        },
+        value:  configured.linker,
+    };
+
+    let sysroot = configured
+        .sysroot
+        .map(|f| format!("--sysroot={}", project_directory.join(f).display()));
+    settings
+        .cflags
+        .value
+        .extend(sysroot.iter().cloned().chain(configured.cflags));
+    settings
+        .ldflags
+        .value
+        .extend(sysroot.into_iter().chain(configured.ldflags));
+
+    Ok(())
+}
+
+/// Returns whether `program` is an executable file in one of the directories of `PATH`.
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|f| {
+        env::split_paths(&f).any(|f| {
+            f.join(program)
+                .metadata()
+                .is_ok_and(|f| f.is_file() && f.permissions().mode() & 0o111 != 0)
+        })
+    })
+}
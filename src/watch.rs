@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use glob::Pattern;
+use walkdir::WalkDir;
+
+use crate::compiler::Language;
+
+/// How often the watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// How long the watched files have to stay unchanged before a change is acted upon, since editors often save a file
+/// with several writes in quick succession.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Extensions of the headers that are watched, besides sources.
+const HEADER_EXTENSIONS: [&str; 4] = ["h", "hh", "hpp", "hxx"];
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// What is watched: the manifest, and every source and header in the directories.
+#[derive(Clone, Debug)]
+pub struct Watched {
+    pub manifest:    PathBuf,
+    pub directories: Vec<PathBuf>,
+    /// Never looked into, even inside a watched directory, so what a build writes can't trigger another build.
+    pub ignored:     PathBuf,
+    /// Files and directories that aren't watched either, as the manifest's `watch-ignore` configures.
+    pub patterns:    Vec<Pattern>,
+}
+
+/// The modification time and size of every watched file. Comparing two snapshots tells whether anything was created,
+/// modified, or deleted in between.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    files: BTreeMap<PathBuf, (Option<SystemTime>, u64)>,
+}
+
+impl Snapshot {
+    pub fn take(watched: &Watched) -> Self {
+        let mut files = BTreeMap::new();
+        let mut record = |path: &Path| {
+            if let Ok(metadata) = fs::metadata(path) {
+                files.insert(path.to_owned(), (metadata.modified().ok(), metadata.len()));
+            }
+        };
+
+        record(&watched.manifest);
+        for directory in &watched.directories {
+            // Hidden directories such as `.git` are skipped too, though not a watched directory itself.
+            let entries = WalkDir::new(directory).into_iter().filter_entry(|f| {
+                f.path() != watched.ignored
+                    && (f.depth() == 0 || !f.file_name().to_string_lossy().starts_with('.'))
+                    && !watched.patterns.iter().any(|pattern| pattern.matches_path(f.path()))
+            });
+            // Entries that can't be read are skipped rather than failing the watch; the build reports them.
+            for entry in entries.flatten() {
+                if entry.file_type().is_file() && is_watched(entry.path()) {
+                    record(entry.path());
+                }
+            }
+        }
+
+        Self {
+            files,
+        }
+    }
+
+    /// Returns a file that differs between the snapshots, if any, preferring one that exists in `self`.
+    pub fn changed<'a>(&'a self, previous: &'a Snapshot) -> Option<&'a Path> {
+        self.files
+            .iter()
+            .find(|(path, state)| previous.files.get(*path) != Some(state))
+            .map(|(path, _)| path.as_path())
+            .or_else(|| {
+                previous
+                    .files
+                    .keys()
+                    .find(|f| !self.files.contains_key(*f))
+                    .map(|f| f.as_path())
+            })
+    }
+}
+
+fn is_watched(path: &Path) -> bool {
+    Language::of(path).is_some()
+        || path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|f| HEADER_EXTENSIONS.contains(&f))
+}
+
+/// Waits until the files `watched` returns differ from `previous` and have then settled, and returns the first one
+/// that changed. Since `watched` is called on every check, the set of watched directories follows the manifest. Returns
+/// `None` if the user interrupted the wait.
+pub fn wait_for_change(previous: &Snapshot, watched: impl Fn() -> Watched) -> Option<PathBuf> {
+    loop {
+        if interrupted() {
+            return None;
+        }
+
+        let mut current = Snapshot::take(&watched());
+        let Some(changed) = current.changed(previous).map(Path::to_owned) else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        loop {
+            thread::sleep(DEBOUNCE);
+            if interrupted() {
+                return None;
+            }
+
+            let next = Snapshot::take(&watched());
+            if next == current {
+                return Some(changed);
+            }
+            current = next;
+        }
+    }
+}
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Makes Ctrl-C end the watch once the current build or wait has noticed, instead of killing Loki in the middle of
+/// writing something. The tools a build runs still receive the interrupt and stop.
+pub fn handle_interrupts() {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// The current local time as `HH:MM:SS`.
+pub fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as libc::time_t;
+
+    // SAFETY: `localtime_r` only writes to the `tm` it is given, and an all-zero `tm` is a valid value.
+    let time = unsafe {
+        let mut time = std::mem::zeroed::<libc::tm>();
+        libc::localtime_r(&now, &mut time);
+        time
+    };
+
+    format!("{:02}:{:02}:{:02}", time.tm_hour, time.tm_min, time.tm_sec)
+}
@@ -0,0 +1,521 @@
+mod support;
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::process::Command;
+
+use support::Fixture;
+
+#[test]
+fn builds_every_source_and_links_them() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let mut compiled = run.compiled();
+    compiled.sort();
+    assert_eq!(compiled, ["greeting.c", "main.c"]);
+    assert!(run.linked());
+    assert!(fixture.path("target/debug/hello").is_file());
+}
+
+#[test]
+fn rebuild_without_changes_compiles_nothing() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+}
+
+#[test]
+fn changing_a_source_recompiles_only_that_source() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let source = fixture.read("src/greeting.c");
+    fixture.write("src/greeting.c", &format!("{}\n/* changed */\n", source));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled(), ["greeting.c"]);
+}
+
+#[test]
+fn failing_source_is_reported() {
+    let fixture = Fixture::new("hello");
+    fixture.write("src/broken.c", "FAKE_ERROR\n");
+
+    let run = fixture.loki(&["build"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("broken.c:1:1: error: fake error"));
+    assert!(run.stderr().contains("1 errors"));
+}
+
+/// Adds two warnings to `greeting.c`.
+fn with_warnings(fixture: &Fixture) {
+    let source = fixture.read("src/greeting.c");
+    fixture.write(
+        "src/greeting.c",
+        &format!("{}\n/* FAKE_WARNING */\n/* FAKE_WARNING */\n", source),
+    );
+}
+
+#[test]
+fn human_summary_counts_steps_warnings_and_errors() {
+    let fixture = Fixture::new("hello");
+    with_warnings(&fixture);
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(
+        run.stderr()
+            .contains("2 compiled, 0 skipped, 1 relinked, 2 warnings, 0 errors"),
+        "{}",
+        run.stderr()
+    );
+
+    fixture.write("src/broken.c", "FAKE_ERROR\n");
+    let run = fixture.loki(&["build", "--keep-going", "--max-warnings", "10"]);
+    assert!(!run.success());
+    assert!(
+        run.stderr()
+            .contains("1 compiled, 2 skipped, 0 relinked, 2 warnings, 1 errors"),
+        "{}",
+        run.stderr()
+    );
+}
+
+#[test]
+fn json_summary_counts_steps_warnings_and_errors() {
+    let fixture = Fixture::new("hello");
+    with_warnings(&fixture);
+
+    let run = fixture.loki(&["build", "--output", "json"]);
+    run.assert_success();
+    let summary: serde_json::Value = serde_json::from_slice(&run.output.stdout).unwrap();
+    assert_eq!(summary["success"], true);
+    assert_eq!(summary["compiled"], 2);
+    assert_eq!(summary["skipped"], 0);
+    assert_eq!(summary["relinked"], 1);
+    assert_eq!(summary["warnings"], 2);
+    assert_eq!(summary["errors"], 0);
+
+    fixture.write("src/broken.c", "FAKE_ERROR\n");
+    let run = fixture.loki(&["build", "--output", "json", "--keep-going", "--max-warnings", "10"]);
+    assert!(!run.success());
+    let summary: serde_json::Value = serde_json::from_slice(&run.output.stdout).unwrap();
+    assert_eq!(summary["success"], false);
+    assert_eq!(summary["compiled"], 1);
+    assert_eq!(summary["skipped"], 2);
+    assert_eq!(summary["relinked"], 0);
+    assert_eq!(summary["warnings"], 2);
+    assert_eq!(summary["errors"], 1);
+    assert_eq!(summary["failures"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn exceeding_the_warning_budget_fails_and_lists_the_noisiest_sources() {
+    let fixture = Fixture::new("hello");
+    with_warnings(&fixture);
+    let main = fixture.read("src/main.c");
+    fixture.write("src/main.c", &format!("{}\n/* FAKE_WARNING */\n", main));
+
+    fixture.loki(&["build", "--max-warnings", "3"]).assert_success();
+
+    let run = fixture.loki(&["build", "--max-warnings", "2"]);
+    assert!(!run.success());
+    let stderr = run.stderr();
+    assert!(
+        stderr.contains("3 warnings exceed the budget of 2 set by 'max-warnings'"),
+        "{}",
+        stderr
+    );
+    let greeting = stderr.find("greeting.c: 2").expect(&stderr);
+    let main = stderr.find("main.c: 1").expect(&stderr);
+    assert!(greeting < main, "{}", stderr);
+    assert!(
+        run.invocations.iter().all(|f| !f.iter().any(|f| f.ends_with(".c"))),
+        "warnings of fresh objects are counted without compiling them again: {:?}",
+        run.invocations
+    );
+
+    let run = fixture.loki(&["build", "--max-warnings", "2", "-q"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("exceed the budget"), "{}", run.stderr());
+    assert!(!run.stderr().contains("greeting.c: 2"), "{}", run.stderr());
+
+    fixture.edit_manifest(|f| format!("{}\n[build]\nmax-warnings = 2\n", f));
+    assert!(!fixture.loki(&["build"]).success());
+}
+
+#[test]
+fn object_without_recorded_warnings_is_compiled_again_to_count_them() {
+    let fixture = Fixture::new("hello");
+    with_warnings(&fixture);
+    fixture.loki(&["build"]).assert_success();
+    for record in fixture.objects().iter().filter(|f| f.ends_with(".warnings")) {
+        std::fs::remove_file(fixture.path(&format!("target/debug/obj/{}", record))).unwrap();
+    }
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty(), "without a budget, warnings aren't counted");
+
+    let run = fixture.loki(&["build", "--max-warnings", "1"]);
+    assert!(!run.success());
+    assert_eq!(run.compiled().len(), 2);
+    assert!(
+        run.stderr().contains("2 warnings exceed the budget of 1"),
+        "{}",
+        run.stderr()
+    );
+}
+
+#[test]
+fn update_warning_budget_sets_max_warnings_to_the_warnings_produced() {
+    let fixture = Fixture::new("hello");
+    with_warnings(&fixture);
+    fixture.edit_manifest(|f| format!("# keep this comment\n{}\n[build]\nmax-warnings = 0\n", f));
+
+    let run = fixture.loki(&["build", "--update-warning-budget"]);
+    run.assert_success();
+    assert!(run.stderr().contains("'max-warnings' to 2 in"), "{}", run.stderr());
+    let manifest = fixture.read("loki.toml");
+    assert!(manifest.starts_with("# keep this comment\n"));
+    assert!(
+        manifest.contains("max-warnings = 2") && !manifest.contains("max-warnings = 0"),
+        "{}",
+        manifest
+    );
+
+    fixture.loki(&["build"]).assert_success();
+}
+
+#[test]
+fn changed_compiler_rebuilds_every_object() {
+    let mut fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    fixture.env.push(("FAKE_CLANG_VERSION".to_owned(), "2.0.0".to_owned()));
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled().len(), 2);
+    assert!(run.stderr().contains("the compiler changed since the last build"));
+}
+
+#[test]
+fn changed_compiler_keeps_objects_without_track_compiler() {
+    let mut fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\ntrack-compiler = false\n", f));
+    fixture.loki(&["build"]).assert_success();
+
+    fixture.env.push(("FAKE_CLANG_VERSION".to_owned(), "2.0.0".to_owned()));
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+}
+
+#[test]
+#[cfg(not(target_os = "macos"))]
+fn split_debug_writes_dwo_files_next_to_objects() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("[configuration]", "[configuration]\nsplit-debug = true"));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run
+        .invocations
+        .iter()
+        .filter(|f| f.iter().any(|f| f == "-c"))
+        .all(|f| f.iter().any(|f| f == "-gsplit-dwarf")));
+
+    let objects = fixture.objects();
+    assert_eq!(objects.iter().filter(|f| f.ends_with(".o")).count(), 2);
+    for object in objects.iter().filter(|f| f.ends_with(".o")) {
+        assert!(objects.contains(&format!("{}.dwo", object)));
+    }
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn split_debug_bundles_debug_info_next_to_the_binary() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("[configuration]", "[configuration]\nsplit-debug = true"));
+
+    fixture.loki(&["build"]).assert_success();
+    assert!(fixture.path("target/debug/hello.dSYM").is_dir());
+}
+
+/// Builds the fixture, changes its manifest with `edit`, and returns the sources the next build compiles.
+fn compiled_after_editing_the_manifest(edit: impl FnOnce(&str) -> String) -> Vec<String> {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    fixture.edit_manifest(edit);
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let mut compiled = run.compiled();
+    compiled.sort();
+    compiled
+}
+
+#[test]
+fn editing_a_key_that_reaches_no_command_line_keeps_every_object() {
+    let compiled = compiled_after_editing_the_manifest(|f| f.replace("version = \"0.1.0\"", "version = \"0.2.0\""));
+    assert!(compiled.is_empty());
+}
+
+#[test]
+fn editing_defines_recompiles_every_source() {
+    let compiled = compiled_after_editing_the_manifest(|f| format!("{}\n[build]\ncflags = [\"-DLOUD=1\"]\n", f));
+    assert_eq!(compiled, ["greeting.c", "main.c"]);
+}
+
+#[test]
+fn adding_a_launcher_keeps_every_object() {
+    let compiled = compiled_after_editing_the_manifest(|f| format!("{}\n[build]\nlauncher = \"env\"\n", f));
+    assert!(compiled.is_empty());
+}
+
+#[test]
+fn adding_werror_recompiles_every_source() {
+    let compiled = compiled_after_editing_the_manifest(|f| format!("{}\n[build]\ncflags = [\"-Werror\"]\n", f));
+    assert_eq!(compiled, ["greeting.c", "main.c"]);
+
+    let compiled = compiled_after_editing_the_manifest(|f| format!("{}\n[build]\ncflags = [\"-Wshadow\"]\n", f));
+    assert!(compiled.is_empty());
+}
+
+#[test]
+fn changing_a_header_recompiles_every_source_including_it() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let header = fixture.read("include/greeting.h");
+    fixture.write("include/greeting.h", &format!("{}\n/* changed */\n", header));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let mut compiled = run.compiled();
+    compiled.sort();
+    assert_eq!(compiled, ["greeting.c", "main.c"]);
+    assert!(run.linked());
+}
+
+#[test]
+fn corrupt_depfile_recompiles_its_source() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    for depfile in fixture.objects().iter().filter(|f| f.ends_with(".d")) {
+        fixture.write(&format!("target/debug/obj/{}", depfile), "not a dependency file");
+    }
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled().len(), 2);
+}
+
+#[test]
+fn source_dated_in_the_future_is_rebuilt_with_a_warning() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let future = std::time::SystemTime::now() + std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(fixture.path("src/greeting.c"))
+        .unwrap()
+        .set_modified(future)
+        .unwrap();
+
+    for _ in 0..2 {
+        let run = fixture.loki(&["build"]);
+        run.assert_success();
+        assert_eq!(run.compiled(), ["greeting.c"]);
+        assert_eq!(
+            run.stderr().matches("is dated in the future").count(),
+            1,
+            "{}",
+            run.stderr()
+        );
+    }
+}
+
+#[test]
+fn rebuild_without_changes_skips_linking() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.invocations.iter().all(|f| !f.iter().any(|f| f == "-c")));
+    assert!(!run.linked());
+}
+
+/// The most compiles that ran at once building `hello` with `jobs`, with `weights` as the `[weights]` table.
+fn most_concurrent_compiles(jobs: &str, weights: &str) -> usize {
+    let mut fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[weights]\n{}\n", f, weights));
+    most_concurrent(&mut fixture, &["build", "-j", jobs])
+}
+
+/// The most compiles that ran at once running Loki in `fixture` with `arguments`.
+fn most_concurrent(fixture: &mut Fixture, arguments: &[&str]) -> usize {
+    let running = fixture.scratch("running");
+    fixture
+        .env
+        .push(("FAKE_CLANG_RUNNING".to_owned(), running.display().to_string()));
+
+    fixture.loki(arguments).assert_success();
+
+    std::fs::read_to_string(running.with_extension("counts"))
+        .unwrap()
+        .lines()
+        .map(|f| f.trim().parse::<usize>().unwrap())
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn compiles_sources_concurrently_up_to_jobs() {
+    assert_eq!(most_concurrent_compiles("2", ""), 2);
+}
+
+#[test]
+fn compiles_one_source_at_a_time_with_one_job() {
+    assert_eq!(most_concurrent_compiles("1", ""), 1);
+}
+
+#[test]
+fn weights_count_against_the_jobs_budget() {
+    assert_eq!(most_concurrent_compiles("2", "\"src/greeting.c\" = 2"), 1);
+    assert_eq!(most_concurrent_compiles("3", "\"src/greeting.c\" = 2"), 2);
+    assert_eq!(most_concurrent_compiles("2", "\"src/*.c\" = 1\n\"src/g*.c\" = 2"), 1);
+}
+
+#[test]
+fn source_heavier_than_the_budget_still_compiles_alone() {
+    assert_eq!(most_concurrent_compiles("2", "\"src/main.c\" = 8"), 1);
+}
+
+#[test]
+fn invalid_weights_are_rejected() {
+    for (weights, error) in [
+        ("\"src/[\" = 2", "invalid pattern 'src/[' in 'weights'"),
+        ("\"src/main.c\" = 0", "nonzero"),
+    ] {
+        let fixture = Fixture::new("hello");
+        fixture.edit_manifest(|f| format!("{}\n[weights]\n{}\n", f, weights));
+
+        let run = fixture.loki(&["build"]);
+        assert!(!run.success(), "{}", weights);
+        assert!(run.stderr().contains(error), "{}", run.stderr());
+    }
+}
+
+/// A pipe holding `tokens` jobserver tokens, as make creates one, with both ends left open for Loki to inherit. Both
+/// are closed when it is dropped.
+struct JobserverPipe {
+    read:  File,
+    write: File,
+}
+
+impl JobserverPipe {
+    fn new(tokens: usize) -> Self {
+        let mut fds = [0; 2];
+        // SAFETY: `pipe` is given room for exactly two file descriptors, which are owned by the files made of them.
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (read, mut write) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+        write.write_all(&vec![b'+'; tokens]).unwrap();
+        Self {
+            read,
+            write,
+        }
+    }
+
+    fn makeflags(&self) -> String {
+        format!(
+            "-j --jobserver-auth={},{}",
+            self.read.as_raw_fd(),
+            self.write.as_raw_fd()
+        )
+    }
+}
+
+/// A fixture for `hello` run under the jobserver `makeflags` name.
+fn under_make(makeflags: String) -> Fixture {
+    let mut fixture = Fixture::new("hello");
+    fixture.env.push(("MAKEFLAGS".to_owned(), makeflags));
+    fixture
+}
+
+#[test]
+fn jobserver_without_tokens_compiles_one_source_at_a_time() {
+    let pipe = JobserverPipe::new(0);
+    let mut fixture = under_make(pipe.makeflags());
+
+    assert_eq!(most_concurrent(&mut fixture, &["build", "-j", "2"]), 1);
+}
+
+#[test]
+fn jobserver_tokens_allow_more_than_jobs() {
+    let pipe = JobserverPipe::new(1);
+    let mut fixture = under_make(pipe.makeflags());
+
+    assert_eq!(most_concurrent(&mut fixture, &["build", "-j", "1"]), 2);
+}
+
+#[test]
+fn jobserver_tokens_are_given_back() {
+    let mut fixture = Fixture::new("hello");
+    let fifo = fixture.scratch("make").join("jobserver");
+    assert!(Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+    let mut jobserver = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&fifo)
+        .unwrap();
+    jobserver.write_all(b"++").unwrap();
+    fixture.env.push((
+        "MAKEFLAGS".to_owned(),
+        format!("-j --jobserver-auth=fifo:{}", fifo.display()),
+    ));
+
+    assert_eq!(most_concurrent(&mut fixture, &["build", "-j", "1"]), 2);
+    let mut tokens = [0; 4];
+    assert_eq!(jobserver.read(&mut tokens).unwrap(), 2);
+}
+
+#[test]
+fn no_jobserver_ignores_make() {
+    let pipe = JobserverPipe::new(0);
+    let mut fixture = under_make(pipe.makeflags());
+
+    assert_eq!(
+        most_concurrent(&mut fixture, &["build", "-j", "2", "--no-jobserver"]),
+        2
+    );
+}
+
+#[test]
+fn unavailable_jobserver_falls_back_to_jobs() {
+    let mut fixture = under_make("-j --jobserver-auth=998,999".to_owned());
+
+    assert_eq!(most_concurrent(&mut fixture, &["build", "-j", "2"]), 2);
+    let run = fixture.loki(&["build"]);
+    assert!(
+        run.stderr().contains("mark the make recipe with '+'"),
+        "{}",
+        run.stderr()
+    );
+}
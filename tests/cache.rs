@@ -0,0 +1,96 @@
+mod support;
+
+use std::fs;
+
+use support::Fixture;
+
+/// Changes `greeting.c` so its next build makes a new object, leaving the old one unused in the cache.
+fn edit_greeting(fixture: &Fixture, edit: usize) {
+    let source = fixture.read("src/greeting.c");
+    fixture.write("src/greeting.c", &format!("{}\n/* edit {} */\n", source, edit));
+}
+
+/// The files in the object directory that belong to `greeting.c`, out of `names`. The warnings recorded for an object
+/// only hold a count, so they are told apart by the object they are named after.
+fn of_greeting(fixture: &Fixture, names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|f| {
+            let object = f.strip_suffix(".warnings").unwrap_or(f);
+            fixture
+                .read(&format!("target/debug/obj/{}", object))
+                .contains("greeting.c")
+        })
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn build_reports_cache_hits_and_misses() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.stderr().contains("0 hits, 2 misses, 0 evicted"), "{}", run.stderr());
+
+    edit_greeting(&fixture, 1);
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.stderr().contains("1 hit, 1 miss, 0 evicted"), "{}", run.stderr());
+
+    let run = fixture.loki(&["build", "-q"]);
+    assert!(!run.stderr().contains("hit"), "{}", run.stderr());
+}
+
+#[test]
+fn cache_max_size_evicts_the_least_recently_used_objects_first() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+    let oldest = of_greeting(&fixture, &fixture.objects());
+
+    edit_greeting(&fixture, 1);
+    let before = fixture.objects();
+    fixture.loki(&["build"]).assert_success();
+    let older = fixture
+        .objects()
+        .into_iter()
+        .filter(|f| !before.contains(f))
+        .collect::<Vec<_>>();
+
+    edit_greeting(&fixture, 2);
+    fixture.loki(&["build"]).assert_success();
+    assert_eq!(fixture.objects().len(), 12);
+    assert_eq!((oldest.len(), older.len()), (3, 3));
+
+    // Builds within the same second use their objects at the same time, so the order is made explicit.
+    let cache = fixture.read("target/debug/.loki/cache.json");
+    let mut cache: serde_json::Value = serde_json::from_str(&cache).unwrap();
+    for (names, last_used) in [(&oldest, 100), (&older, 200)] {
+        for name in names.iter().filter(|f| f.ends_with(".o")) {
+            cache["last_used"][name] = last_used.into();
+        }
+    }
+    fixture.write("target/debug/.loki/cache.json", &cache.to_string());
+
+    let size = |names: &[String]| {
+        names
+            .iter()
+            .map(|f| {
+                fs::metadata(fixture.path(&format!("target/debug/obj/{}", f)))
+                    .unwrap()
+                    .len()
+            })
+            .sum::<u64>()
+    };
+    let limit = size(&fixture.objects()) - size(&oldest);
+    fixture.edit_manifest(|f| format!("{}\n[build]\ncache-max-size = {}\n", f, limit));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.stderr().contains("2 hits, 0 misses, 3 evicted"), "{}", run.stderr());
+
+    let objects = fixture.objects();
+    assert_eq!(objects.len(), 9);
+    assert!(oldest.iter().all(|f| !objects.contains(f)));
+    assert!(older.iter().all(|f| objects.contains(f)));
+}
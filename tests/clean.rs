@@ -0,0 +1,75 @@
+mod support;
+
+use std::fs;
+use std::os::unix::fs::symlink;
+
+use support::Fixture;
+
+#[test]
+fn removes_the_target_directory() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let run = fixture.loki(&["clean"]);
+    run.assert_success();
+    assert!(run.stderr().contains("Removed"));
+    assert!(!fixture.path("target").exists());
+    assert!(fixture.path("src/main.c").exists());
+}
+
+#[test]
+fn objects_keeps_the_binary() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    fixture.loki(&["clean", "--objects"]).assert_success();
+    assert!(!fixture.path("target/debug/obj").exists());
+    assert!(fixture.path("target/debug/hello").is_file());
+}
+
+#[test]
+fn objects_and_state_clean_only_the_selected_profile() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+    fixture.loki(&["build", "--release"]).assert_success();
+
+    fixture.loki(&["clean", "--objects", "--release"]).assert_success();
+    assert!(!fixture.path("target/release/obj").exists());
+    assert!(fixture.path("target/debug/obj").is_dir());
+
+    fixture.loki(&["clean", "--state"]).assert_success();
+    assert!(!fixture.path("target/debug/.loki").exists());
+    assert!(fixture.path("target/release/.loki").is_dir());
+}
+
+#[test]
+fn nothing_to_clean_is_not_an_error() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["clean"]);
+    run.assert_success();
+    assert!(run.stderr().contains("does not exist"));
+}
+
+#[test]
+fn symlinked_target_is_unlinked_without_touching_its_destination() {
+    let fixture = Fixture::new("hello");
+    let elsewhere = fixture.scratch("elsewhere");
+    fs::write(elsewhere.join("keep"), "").unwrap();
+    symlink(&elsewhere, fixture.path("target")).unwrap();
+
+    fixture.loki(&["clean"]).assert_success();
+    assert!(fs::symlink_metadata(fixture.path("target")).is_err());
+    assert!(elsewhere.join("keep").exists());
+}
+
+#[test]
+fn refuses_to_run_outside_a_project() {
+    let fixture = Fixture::new("hello");
+    fs::remove_file(fixture.path("loki.toml")).unwrap();
+    fs::create_dir_all(fixture.path("target")).unwrap();
+
+    let run = fixture.loki(&["clean"]);
+    assert!(!run.success());
+    assert!(fixture.path("target").exists());
+}
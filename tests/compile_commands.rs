@@ -0,0 +1,62 @@
+mod support;
+
+use support::Fixture;
+
+fn compile_commands(fixture: &Fixture) -> Vec<serde_json::Value> {
+    serde_json::from_str(&fixture.read("target/compile_commands.json")).unwrap()
+}
+
+#[test]
+fn build_writes_an_entry_per_source_as_it_is_compiled() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let commands = compile_commands(&fixture);
+    assert_eq!(commands.len(), 2);
+    for command in &commands {
+        let file = command["file"].as_str().unwrap();
+        let arguments = command["arguments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f.as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        let compile = run.invocations.iter().find(|f| f.iter().any(|f| f == file)).unwrap();
+
+        assert_eq!(arguments[0], "clang");
+        // Only the dependency file and the temporary the object is compiled to are left out.
+        assert!(compile
+            .iter()
+            .all(|f| f == "-MMD" || f == "-MF" || f.contains(".tmp-") || arguments.contains(f)));
+        assert_eq!(
+            command["directory"].as_str().unwrap(),
+            fixture.path("").canonicalize().unwrap().to_str().unwrap()
+        );
+        assert!(std::path::Path::new(command["output"].as_str().unwrap()).is_file());
+    }
+}
+
+#[test]
+fn up_to_date_sources_keep_their_entries() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+    let before = fixture.read("target/compile_commands.json");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+    assert_eq!(fixture.read("target/compile_commands.json"), before);
+}
+
+#[test]
+fn launcher_is_left_out() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\nlauncher = \"env\"\n", f));
+
+    fixture.loki(&["build"]).assert_success();
+    for command in compile_commands(&fixture) {
+        assert_eq!(command["arguments"][0], "clang");
+    }
+}
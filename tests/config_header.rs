@@ -0,0 +1,99 @@
+mod support;
+
+use std::fs;
+
+use support::Fixture;
+
+/// Adds a `[config-header]` table with `defines`, and makes `main.c` include the header.
+fn with_config_header(fixture: &Fixture, defines: &str) {
+    fixture.edit_manifest(|f| {
+        format!(
+            "{}\n[config-header]\ndefines = {{ {} }}\nchecks = {{ HAVE_UNISTD_H = {{ header = \"unistd.h\" }} }}\n",
+            f.split("\n[config-header]").next().unwrap(),
+            defines
+        )
+    });
+
+    let source = fixture.read("src/main.c");
+    if !source.contains("config.h") {
+        fixture.write("src/main.c", &format!("#include \"config.h\"\n{}", source));
+    }
+}
+
+/// The feature checks `run` ran, each a compile of standard input.
+fn checks(run: &support::Run) -> usize {
+    run.invocations
+        .iter()
+        .filter(|f| f.last().is_some_and(|f| f == "-") && !f.iter().any(|f| f == "-Werror"))
+        .count()
+}
+
+#[test]
+fn header_is_generated_from_the_defines_and_checks() {
+    let fixture = Fixture::new("hello");
+    with_config_header(
+        &fixture,
+        "VERSION = \"1.0\", USE_COLOR = true, TRACE = false, LEVEL = 2",
+    );
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.stderr().contains("Generated"));
+    assert_eq!(checks(&run), 1);
+
+    let header = fixture.read("target/include/config.h");
+    assert!(header.starts_with("/* Generated by Loki from inputs "));
+    for line in [
+        "#define LEVEL 2",
+        "/* #undef TRACE */",
+        "#define USE_COLOR 1",
+        "#define VERSION \"1.0\"",
+        "#define HAVE_UNISTD_H 1",
+    ] {
+        assert!(header.lines().any(|f| f == line), "{}\n{}", line, header);
+    }
+}
+
+#[test]
+fn header_is_rewritten_only_when_its_inputs_change() {
+    let fixture = Fixture::new("hello");
+    with_config_header(&fixture, "LEVEL = 2");
+    fixture.loki(&["build"]).assert_success();
+    let written = fs::metadata(fixture.path("target/include/config.h"))
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(!run.stderr().contains("Generated"));
+    assert_eq!(checks(&run), 0);
+    let modified = fs::metadata(fixture.path("target/include/config.h"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(modified, written);
+
+    with_config_header(&fixture, "LEVEL = 3");
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.stderr().contains("Generated"));
+    assert!(fixture.read("target/include/config.h").contains("#define LEVEL 3"));
+}
+
+#[test]
+fn changed_header_recompiles_only_the_sources_including_it() {
+    let fixture = Fixture::new("hello");
+    with_config_header(&fixture, "LEVEL = 2");
+    fixture.loki(&["build"]).assert_success();
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+
+    with_config_header(&fixture, "LEVEL = 3");
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled(), ["main.c"]);
+    assert!(run.linked());
+}
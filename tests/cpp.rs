@@ -0,0 +1,55 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn mixed_project_compiles_each_language_with_its_driver() {
+    let fixture = Fixture::new("mixed");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let mut compiled = run.compiled();
+    compiled.sort();
+    assert_eq!(compiled, ["main.cpp", "util.c"]);
+    for (program, arguments) in run.programs.iter().zip(&run.invocations) {
+        if arguments.iter().any(|f| f.ends_with("main.cpp")) {
+            assert_eq!(program, "clang++");
+            assert!(arguments.contains(&"-std=c++17".to_owned()));
+        } else if arguments.iter().any(|f| f.ends_with("util.c")) {
+            assert_eq!(program, "clang");
+            assert!(arguments.contains(&"-std=c17".to_owned()));
+        }
+    }
+}
+
+#[test]
+fn mixed_project_links_with_the_cpp_driver_and_runs() {
+    let fixture = Fixture::new("mixed");
+
+    let run = fixture.loki(&["run"]);
+    run.assert_success();
+
+    let link = run
+        .invocations
+        .iter()
+        .position(|f| !f.iter().any(|f| f == "-c") && f.iter().any(|f| f.ends_with(".o")))
+        .unwrap();
+    assert_eq!(run.programs[link], "clang++");
+    assert!(String::from_utf8_lossy(&run.output.stdout).contains("ran in"));
+}
+
+#[test]
+fn cpp_standard_is_left_to_the_compiler_when_unset() {
+    let fixture = Fixture::new("mixed");
+    fixture.edit_manifest(|f| f.replace("cpp-standard = \"c++17\"\n", ""));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let compile = run
+        .invocations
+        .iter()
+        .find(|f| f.iter().any(|f| f.ends_with("main.cpp")))
+        .unwrap();
+    assert!(!compile.iter().any(|f| f.starts_with("-std=")));
+}
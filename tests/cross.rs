@@ -0,0 +1,139 @@
+mod support;
+
+use support::Fixture;
+
+const TRIPLE: &str = "arm-linux-gnueabihf";
+
+#[test]
+fn target_section_configures_the_compiler_sysroot_and_flags() {
+    let fixture = Fixture::new("hello");
+    fixture.install("arm-cc", "clang");
+    fixture.edit_manifest(|f| {
+        format!(
+            "{}\n[target.{}]\ncc = \"arm-cc\"\nsysroot = \"sysroot\"\ncflags = [\"-mthumb\"]\nldflags = \
+             [\"-static\"]\n",
+            f, TRIPLE
+        )
+    });
+
+    let run = fixture.loki(&["build", "--target", TRIPLE]);
+    run.assert_success();
+    assert!(run.programs.iter().all(|f| f == "arm-cc"), "{:?}", run.programs);
+
+    let sysroot = format!("--sysroot={}", fixture.path("sysroot").display());
+    let (links, compiles) = run
+        .invocations
+        .iter()
+        .filter(|f| f.iter().any(|f| f.ends_with(".c") || f.ends_with(".o")))
+        .partition::<Vec<_>, _>(|f| !f.iter().any(|f| f == "-c"));
+    assert_eq!(compiles.len(), 2);
+    for compile in compiles {
+        assert!(compile.contains(&sysroot) && compile.iter().any(|f| f == "-mthumb"));
+        assert!(!compile.iter().any(|f| f == "-static"));
+    }
+    assert!(links[0].contains(&sysroot) && links[0].iter().any(|f| f == "-static"));
+    assert!(!links[0].iter().any(|f| f.starts_with("-fuse-ld")));
+
+    assert!(fixture.path("target/arm-linux-gnueabihf/debug/hello").is_file());
+    assert!(fixture.path("target/arm-linux-gnueabihf/debug/obj").is_dir());
+    assert!(!fixture.path("target/debug").exists());
+}
+
+#[test]
+fn prefixed_gnu_tools_are_used_without_a_target_section() {
+    let fixture = Fixture::new("hello");
+    fixture.install("arm-linux-gnueabihf-gcc", "clang");
+    fixture.install("arm-linux-gnueabihf-ar", "ar");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"staticlib\""));
+
+    let run = fixture.loki(&["build", "--target", TRIPLE]);
+    run.assert_success();
+    let mut programs = run.programs.clone();
+    programs.dedup();
+    assert_eq!(programs, ["arm-linux-gnueabihf-gcc", "arm-linux-gnueabihf-ar"]);
+    assert!(fixture.path("target/arm-linux-gnueabihf/debug/libhello.a").is_file());
+}
+
+#[test]
+fn unknown_target_fails_instead_of_using_the_host_compiler() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build", "--target", "mips-unknown-none"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("no toolchain for the target 'mips-unknown-none'"));
+    assert!(run.stderr().contains("[target.mips-unknown-none]"));
+    assert!(run.invocations.is_empty());
+}
+
+#[test]
+fn host_and_cross_builds_keep_their_own_objects() {
+    let fixture = Fixture::new("hello");
+    fixture.install("arm-linux-gnueabihf-gcc", "clang");
+
+    fixture.loki(&["build"]).assert_success();
+    let run = fixture.loki(&["build", "--target", TRIPLE]);
+    run.assert_success();
+    assert_eq!(run.compiled().len(), 2);
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+    assert!(!run.linked());
+
+    let run = fixture.loki(&["build", "--target", TRIPLE]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+    assert!(!run.linked());
+}
+
+#[test]
+fn cross_build_never_compiles_files_in_target() {
+    let fixture = Fixture::new("hello");
+    fixture.install("arm-linux-gnueabihf-gcc", "clang");
+    fixture.edit_manifest(|f| f.replace("[package]\n", "[package]\nsource-dir = \".\"\n"));
+    fixture.write("target/debug/stray.c", "FAKE_ERROR\n");
+
+    let run = fixture.loki(&["build", "--target", TRIPLE]);
+    run.assert_success();
+    let mut compiled = run.compiled();
+    compiled.sort();
+    assert_eq!(compiled, ["greeting.c", "main.c"]);
+}
+
+/// Builds `hello` with split debug info for `triple`, returning the arguments of its compiles and the programs run.
+fn build_with_split_debug(triple: &str) -> (Vec<Vec<String>>, Vec<String>) {
+    let fixture = Fixture::new("hello");
+    fixture.install("cross-cc", "clang");
+    fixture.edit_manifest(|f| {
+        format!(
+            "{}\n[target.{}]\ncc = \"cross-cc\"\n",
+            f.replace("[configuration]", "[configuration]\nsplit-debug = true"),
+            triple
+        )
+    });
+
+    let run = fixture.loki(&["build", "--target", triple]);
+    run.assert_success();
+    let compiles = run
+        .invocations
+        .iter()
+        .filter(|f| f.iter().any(|f| f == "-c"))
+        .cloned()
+        .collect();
+    (compiles, run.programs)
+}
+
+#[test]
+fn split_dwarf_follows_the_target_rather_than_the_host() {
+    let (compiles, programs) = build_with_split_debug(TRIPLE);
+    assert_eq!(compiles.len(), 2);
+    assert!(compiles.iter().all(|f| f.iter().any(|f| f == "-gsplit-dwarf")));
+    assert!(!programs.iter().any(|f| f == "dsymutil"));
+
+    let (compiles, programs) = build_with_split_debug("aarch64-apple-darwin");
+    assert_eq!(compiles.len(), 2);
+    assert!(compiles
+        .iter()
+        .all(|f| f.iter().any(|f| f == "-g") && !f.iter().any(|f| f == "-gsplit-dwarf")));
+    assert!(programs.iter().any(|f| f == "dsymutil"));
+}
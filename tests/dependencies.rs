@@ -0,0 +1,84 @@
+mod support;
+
+use support::Fixture;
+use support::Run;
+
+/// The arguments of the link, after the last object.
+fn after_objects(run: &Run) -> Vec<String> {
+    let link = run
+        .invocations
+        .iter()
+        .find(|f| f.iter().any(|f| f.ends_with(".o")) && !f.iter().any(|f| f == "-c"))
+        .unwrap();
+    let last = link.iter().rposition(|f| f.ends_with(".o")).unwrap();
+    link[last + 1..].to_vec()
+}
+
+#[test]
+fn libs_are_linked_after_the_objects() {
+    let fixture = Fixture::new("math");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(after_objects(&run), ["-lm"]);
+    assert!(!run.programs.iter().any(|f| f == "pkg-config"));
+}
+
+#[test]
+fn lib_dirs_are_searched_relative_to_the_project() {
+    let fixture = Fixture::new("math");
+    fixture.edit_manifest(|f| f.replace("[dependencies]", "[dependencies]\nlib-dirs = [\"vendor/lib\"]"));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(after_objects(&run), [
+        format!("-L{}", fixture.path("vendor/lib").display()),
+        "-lm".to_owned()
+    ]);
+}
+
+#[test]
+fn pkg_config_flags_are_passed_to_compiles_and_the_link_in_order() {
+    let fixture = Fixture::new("math");
+    fixture.edit_manifest(|f| f.replace("[dependencies]", "[dependencies]\npkg-config = [\"other\", \"fake\"]"));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let compile = run.invocations.iter().find(|f| f.iter().any(|f| f == "-c")).unwrap();
+    let other = compile.iter().position(|f| f == "-I/opt/other/include").unwrap();
+    let fake = compile.iter().position(|f| f == "-I/opt/fake/include").unwrap();
+    assert!(other < fake);
+
+    assert_eq!(after_objects(&run), [
+        "-L/opt/other/lib",
+        "-lother",
+        "-L/opt/fake/lib",
+        "-lfake",
+        "-lm"
+    ]);
+}
+
+#[test]
+fn missing_pkg_config_package_fails_the_build() {
+    let fixture = Fixture::new("math");
+    fixture.edit_manifest(|f| f.replace("[dependencies]", "[dependencies]\npkg-config = [\"fake\", \"missing\"]"));
+
+    let run = fixture.loki(&["build"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("can't find the package 'missing'"));
+    assert!(run.compiled().is_empty());
+}
+
+#[test]
+fn missing_pkg_config_fails_the_build() {
+    let mut fixture = Fixture::new("math");
+    fixture.edit_manifest(|f| f.replace("[dependencies]", "[dependencies]\npkg-config = [\"fake\"]"));
+    fixture
+        .env
+        .push(("PKG_CONFIG".to_owned(), "loki-test-no-such-pkg-config".to_owned()));
+
+    let run = fixture.loki(&["build"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("'loki-test-no-such-pkg-config' is not installed"));
+}
@@ -0,0 +1,57 @@
+mod support;
+
+use support::Fixture;
+
+/// Adds a project called `inner` at `vendor/inner`, inside the fixture's own.
+fn with_inner_project(fixture: &Fixture) {
+    fixture.write(
+        "vendor/inner/loki.toml",
+        "[package]\nname = \"inner\"\ntype = \"binary\"\nversion = \"0.1.0\"\n\n[configuration]\nc-standard = \
+         \"c17\"\nopt-level = 2\n",
+    );
+    fixture.write("vendor/inner/src/main.c", "int main(void) { return 0; }\n");
+}
+
+#[test]
+fn subdirectory_uses_the_project_above_it() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki_in("src", &["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled().len(), 2);
+    assert!(fixture.path("target/debug/hello").is_file());
+    assert!(!fixture.path("src/target").exists());
+}
+
+#[test]
+fn nested_project_uses_its_own_manifest() {
+    let fixture = Fixture::new("hello");
+    with_inner_project(&fixture);
+
+    let run = fixture.loki_in("vendor/inner/src", &["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled(), ["main.c"]);
+    assert!(fixture.path("vendor/inner/target/debug/inner").is_file());
+    assert!(!fixture.path("target").exists());
+
+    let run = fixture.loki_in("vendor", &["build"]);
+    run.assert_success();
+    assert_eq!(run.compiled().len(), 2);
+    assert!(fixture.path("target/debug/hello").is_file());
+}
+
+#[test]
+fn project_dir_overrides_discovery() {
+    let fixture = Fixture::new("hello");
+    with_inner_project(&fixture);
+
+    fixture
+        .loki_in("src", &["build", "--project-dir", "../vendor/inner"])
+        .assert_success();
+    assert!(fixture.path("vendor/inner/target/debug/inner").is_file());
+    assert!(!fixture.path("target").exists());
+
+    let run = fixture.loki_in("src", &["build", "--project-dir", "."]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("no loki.toml found in ."), "{}", run.stderr());
+}
@@ -0,0 +1,7 @@
+#include <stdio.h>
+
+#include "greeting.h"
+
+void greet(void) {
+    puts("Hello!");
+}
@@ -0,0 +1,8 @@
+#include <math.h>
+#include <stdio.h>
+
+int main(int argc, char **argv) {
+    (void)argv;
+    printf("%.1f\n", sqrt(argc * 16.0));
+    return 0;
+}
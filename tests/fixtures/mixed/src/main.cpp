@@ -0,0 +1,8 @@
+#include <iostream>
+
+extern "C" int answer(void);
+
+int main() {
+    std::cout << answer() << std::endl;
+    return 0;
+}
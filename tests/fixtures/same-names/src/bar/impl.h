@@ -0,0 +1 @@
+#define NAME bar_name
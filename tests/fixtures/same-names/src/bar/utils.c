@@ -0,0 +1,5 @@
+#include "impl.h"
+
+int NAME(void) {
+    return 1;
+}
@@ -0,0 +1 @@
+#define NAME foo_name
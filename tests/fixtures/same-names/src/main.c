@@ -0,0 +1,6 @@
+int foo_name(void);
+int bar_name(void);
+
+int main(void) {
+    return foo_name() + bar_name() - 2;
+}
@@ -0,0 +1,136 @@
+mod support;
+
+use support::Fixture;
+
+fn compiles(run: &support::Run) -> Vec<(&String, &Vec<String>)> {
+    run.programs
+        .iter()
+        .zip(&run.invocations)
+        .filter(|(_, f)| f.iter().any(|f| f == "-c"))
+        .collect()
+}
+
+#[test]
+fn manifest_compiler_and_flags_are_passed_after_loki_flags() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| {
+        format!(
+            "{}\n[build]\ncc = \"clang\"\ncflags = [\"-Wall\", \"-Werror\", \"-DGREETING=hello world\"]\n",
+            f
+        )
+    });
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let compiles = compiles(&run);
+    assert_eq!(compiles.len(), 2);
+    for (program, arguments) in compiles {
+        assert_eq!(program, "clang");
+        let position = |flag: &str| arguments.iter().position(|f| f == flag).unwrap();
+        assert!(position("-Wpedantic") < position("-Werror"));
+        assert!(arguments.contains(&"-DGREETING=hello world".to_owned()));
+    }
+}
+
+#[test]
+fn link_flags_are_passed_when_linking() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\nldflags = [\"-Wl,-rpath,/opt/my libs\"]\n", f));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let link = run
+        .invocations
+        .iter()
+        .find(|f| f.iter().any(|f| f.ends_with(".o")) && !f.iter().any(|f| f == "-c"))
+        .unwrap();
+    assert!(link.contains(&"-Wl,-rpath,/opt/my libs".to_owned()));
+    assert!(compiles(&run)
+        .iter()
+        .all(|(_, f)| !f.contains(&"-Wl,-rpath,/opt/my libs".to_owned())));
+}
+
+#[test]
+fn include_dirs_are_searched_relative_to_the_project() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\ninclude-dirs = [\"vendor\"]\n", f));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let flag = format!("-I{}", fixture.path("vendor").display());
+    for (_, arguments) in compiles(&run) {
+        assert!(arguments.contains(&flag), "{:?} lacks {}", arguments, flag);
+    }
+}
+
+#[test]
+fn cc_environment_variable_overrides_the_manifest() {
+    let mut fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\ncc = \"no-such-compiler\"\n", f));
+    fixture.env.push(("CC".to_owned(), "clang".to_owned()));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(compiles(&run).iter().all(|(program, _)| *program == "clang"));
+
+    let run = fixture.loki(&["env"]);
+    assert!(String::from_utf8_lossy(&run.output.stdout).contains("cc       = clang (environment)"));
+}
+
+/// The arguments of every compile in a fresh build of `hello`, with `defaults` set to `set` if it is given.
+fn compiled_with_defaults(set: Option<&str>) -> Vec<Vec<String>> {
+    let fixture = Fixture::new("hello");
+    if let Some(set) = set {
+        fixture.edit_manifest(|f| f.replace("[package]\n", &format!("[package]\ndefaults = \"{}\"\n", set)));
+    }
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    compiles(&run).into_iter().map(|(_, f)| f.clone()).collect()
+}
+
+#[test]
+fn defaults_key_selects_the_implicit_flags() {
+    let has = |arguments: &[String], flag: &str| arguments.iter().any(|f| f == flag);
+
+    for set in [None, Some("2023")] {
+        for arguments in compiled_with_defaults(set) {
+            assert!(has(&arguments, "-Wpedantic") && has(&arguments, "-fwrapv"));
+            assert!(!has(&arguments, "-Wshadow"));
+        }
+    }
+
+    let compiles = compiled_with_defaults(Some("2025"));
+    assert_eq!(compiles.len(), 2);
+    for arguments in compiles {
+        assert!(has(&arguments, "-Wpedantic") && has(&arguments, "-fwrapv"));
+        assert!(has(&arguments, "-Wshadow") && has(&arguments, "-Wformat=2"));
+    }
+}
+
+#[test]
+fn stop_on_first_error_passes_wfatal_errors_to_every_compile() {
+    let fixture = Fixture::new("hello");
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(compiles(&run)
+        .iter()
+        .all(|(_, f)| !f.contains(&"-Wfatal-errors".to_owned())));
+
+    let run = fixture.loki(&["build", "--stop-on-first-error"]);
+    run.assert_success();
+    let fatal = compiles(&run);
+    assert_eq!(fatal.len(), 2);
+    assert!(fatal.iter().all(|(_, f)| f.contains(&"-Wfatal-errors".to_owned())));
+
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\nstop-on-first-error = true\n", f));
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let fatal = compiles(&run);
+    assert_eq!(fatal.len(), 2);
+    assert!(fatal.iter().all(|(_, f)| f.contains(&"-Wfatal-errors".to_owned())));
+}
@@ -0,0 +1,39 @@
+mod support;
+
+use support::Fixture;
+
+/// Both sources share the node creating the object directory, and the link shares the one creating the target
+/// directory with them, so the build graph holds diamonds.
+#[test]
+fn shared_nodes_execute_once_before_every_parent() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build", "-vvv", "-j", "1"]);
+    run.assert_success();
+    let stderr = run.stderr();
+    let traces = stderr
+        .lines()
+        .filter_map(|f| f.trim_start().strip_prefix("Trace "))
+        .collect::<Vec<_>>();
+
+    for directory in ["target/debug'", "target/debug/obj'"] {
+        let executions = traces
+            .iter()
+            .filter(|f| f.starts_with("executing 'create directory") && f.ends_with(directory))
+            .count();
+        assert_eq!(executions, 1, "{} was created {} times", directory, executions);
+    }
+
+    let created = traces
+        .iter()
+        .position(|f| f.starts_with("'create directory") && f.contains("target/debug/obj'"))
+        .unwrap();
+    for source in ["main.c", "greeting.c"] {
+        let compiled = traces
+            .iter()
+            .position(|f| f.starts_with("executing 'compile") && f.ends_with(&format!("{}'", source)))
+            .unwrap();
+        assert!(created < compiled);
+    }
+    assert!(traces.last().unwrap().starts_with("'link"));
+}
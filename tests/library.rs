@@ -0,0 +1,113 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn staticlib_archives_the_objects() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"staticlib\""));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    let archive = run.programs.iter().position(|f| f == "ar").unwrap();
+    let arguments = &run.invocations[archive];
+    assert_eq!(arguments[0], "rcsD");
+    assert_eq!(arguments.iter().filter(|f| f.ends_with(".o")).count(), 2);
+    assert!(run.invocations.iter().all(|f| !f.iter().any(|f| f == "-fPIC")));
+    assert!(fixture.path("target/debug/libhello.a").is_file());
+    assert!(!fixture.path("target/debug/hello").exists());
+
+    let artifacts: serde_json::Value = serde_json::from_str(&fixture.read("target/debug/artifacts.json")).unwrap();
+    assert_eq!(artifacts["artifacts"][0]["kind"], "staticlib");
+}
+
+#[test]
+fn staticlib_archives_are_reproducible() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"staticlib\""));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let archive = run.programs.iter().position(|f| f == "ar").unwrap();
+    let members = &run.invocations[archive][2..];
+    let mut sorted = members.to_vec();
+    sorted.sort();
+    assert_eq!(members, sorted);
+
+    let first = std::fs::read(fixture.path("target/debug/libhello.a")).unwrap();
+    std::fs::remove_file(fixture.path("target/debug/libhello.a")).unwrap();
+    fixture.loki(&["build"]).assert_success();
+    assert_eq!(std::fs::read(fixture.path("target/debug/libhello.a")).unwrap(), first);
+}
+
+#[test]
+fn staticlib_rebuild_without_changes_skips_archiving() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"staticlib\""));
+    fixture.loki(&["build"]).assert_success();
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(!run.programs.iter().any(|f| f == "ar"));
+}
+
+#[test]
+#[cfg(not(target_os = "macos"))]
+fn sharedlib_links_position_independent_objects() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"sharedlib\""));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+
+    for compile in run.invocations.iter().filter(|f| f.iter().any(|f| f == "-c")) {
+        assert!(compile.iter().any(|f| f == "-fPIC"));
+    }
+    let link = run
+        .invocations
+        .iter()
+        .find(|f| f.iter().any(|f| f.ends_with(".o")) && !f.iter().any(|f| f == "-c"))
+        .unwrap();
+    assert!(link.iter().any(|f| f == "-shared"));
+    assert!(fixture.path("target/debug/libhello.so").is_file());
+}
+
+#[test]
+fn bin_is_the_same_as_binary() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+    let objects = fixture.objects();
+    std::fs::remove_dir_all(fixture.path("target")).unwrap();
+
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"bin\""));
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    // Objects are named after their command lines, so the same names mean the same compiles.
+    assert_eq!(fixture.objects(), objects);
+    assert!(run.linked());
+    assert!(fixture.path("target/debug/hello").is_file());
+}
+
+#[test]
+fn unknown_type_is_an_error() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"library\""));
+
+    let run = fixture.loki(&["build"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("unknown variant `library`"));
+    assert!(run.invocations.is_empty());
+}
+
+#[test]
+fn run_refuses_a_library() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"staticlib\""));
+
+    let run = fixture.loki(&["run"]);
+    assert!(!run.success());
+    assert!(run
+        .stderr()
+        .contains("'run' needs a binary, but hello is a staticlib project"));
+}
@@ -0,0 +1,29 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn lists_the_binary_without_building() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["list"]);
+    run.assert_success();
+    assert_eq!(
+        String::from_utf8_lossy(&run.output.stdout),
+        "hello (bin) target/debug/hello [default]\n"
+    );
+    assert!(run.invocations.is_empty());
+    assert!(!fixture.path("target").exists());
+}
+
+#[test]
+fn lists_targets_as_json() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["list", "--output", "json"]);
+    run.assert_success();
+    let targets: serde_json::Value = serde_json::from_slice(&run.output.stdout).unwrap();
+    assert_eq!(targets[0]["name"], "hello");
+    assert_eq!(targets[0]["kind"], "bin");
+    assert_eq!(targets[0]["default"], true);
+}
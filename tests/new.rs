@@ -0,0 +1,51 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn new_project_builds() {
+    let fixture = Fixture::new("hello");
+
+    fixture.loki(&["new", "demo"]).assert_success();
+    assert!(fixture.path("demo/src/main.c").is_file());
+
+    let run = fixture.loki(&["build", "--project-dir", "demo"]);
+    run.assert_success();
+    assert_eq!(run.compiled(), ["main.c"]);
+    assert!(fixture.path("demo/target/debug/demo").is_file());
+}
+
+#[test]
+fn flat_project_keeps_main_next_to_the_manifest() {
+    let fixture = Fixture::new("hello");
+
+    fixture.loki(&["new", "demo", "--flat"]).assert_success();
+    assert!(fixture.path("demo/main.c").is_file());
+    assert!(!fixture.path("demo/src").exists());
+
+    let run = fixture.loki(&["build", "--project-dir", "demo"]);
+    run.assert_success();
+    assert_eq!(run.compiled(), ["main.c"]);
+}
+
+#[test]
+fn init_refuses_to_overwrite_an_existing_manifest() {
+    let fixture = Fixture::new("hello");
+    let manifest = fixture.read("loki.toml");
+
+    let run = fixture.loki(&["init"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("loki.toml already exists"));
+    assert_eq!(fixture.read("loki.toml"), manifest);
+}
+
+#[test]
+fn new_rejects_invalid_names() {
+    let fixture = Fixture::new("hello");
+
+    for name in ["my project", "a/b", "1st", ""] {
+        let run = fixture.loki(&["new", name]);
+        assert!(!run.success(), "accepted '{}'", name);
+        assert!(run.stderr().contains("is not a valid project name"));
+    }
+}
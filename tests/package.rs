@@ -0,0 +1,95 @@
+mod support;
+
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+
+use flate2::read::GzDecoder;
+use support::Fixture;
+
+/// The only archive in `target/package`.
+fn archive(fixture: &Fixture) -> PathBuf {
+    let mut archives = fs::read_dir(fixture.path("target/package"))
+        .unwrap()
+        .map(|f| f.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(archives.len(), 1, "{:?}", archives);
+    archives.remove(0)
+}
+
+/// The paths of the entries in the `.tar.gz` at `path`.
+fn entries(path: &PathBuf) -> Vec<String> {
+    tar::Archive::new(GzDecoder::new(File::open(path).unwrap()))
+        .entries()
+        .unwrap()
+        .map(|f| f.unwrap().path().unwrap().display().to_string())
+        .collect()
+}
+
+#[test]
+fn binary_package_holds_the_binary_without_headers() {
+    let fixture = Fixture::new("hello");
+
+    fixture.loki(&["package"]).assert_success();
+    assert_eq!(entries(&archive(&fixture)), [
+        "hello-0.1.0/hello",
+        "hello-0.1.0/SHA256SUMS"
+    ]);
+}
+
+#[test]
+fn library_packages_include_the_public_headers() {
+    for (kind, library) in [("staticlib", "libhello.a"), ("sharedlib", "libhello.so")] {
+        let fixture = Fixture::new("hello");
+        fixture.edit_manifest(|f| f.replace("type = \"binary\"", &format!("type = \"{}\"", kind)));
+        fixture.write("include/detail/inner.h", "#pragma once\n");
+
+        fixture.loki(&["package"]).assert_success();
+        let entries = entries(&archive(&fixture));
+        assert_eq!(
+            entries,
+            [
+                "hello-0.1.0/include/detail/inner.h".to_owned(),
+                "hello-0.1.0/include/greeting.h".to_owned(),
+                format!("hello-0.1.0/{}", library),
+                "hello-0.1.0/SHA256SUMS".to_owned(),
+            ],
+            "{}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn packaging_twice_with_source_date_epoch_is_reproducible() {
+    for format in ["tar.gz", "zip"] {
+        let mut fixture = Fixture::new("hello");
+        fixture.edit_manifest(|f| f.replace("type = \"binary\"", "type = \"staticlib\""));
+        fixture
+            .env
+            .push(("SOURCE_DATE_EPOCH".to_owned(), "1700000000".to_owned()));
+
+        fixture.loki(&["package", "--format", format]).assert_success();
+        let first = fs::read(archive(&fixture)).unwrap();
+
+        // Everything is built again, and the headers get new modification times.
+        fixture.loki(&["clean"]).assert_success();
+        fixture.write("include/greeting.h", &fixture.read("include/greeting.h"));
+        fixture.loki(&["package", "--format", format]).assert_success();
+        assert_eq!(fs::read(archive(&fixture)).unwrap(), first, "{}", format);
+    }
+}
+
+#[test]
+fn failed_package_leaves_no_partial_archive() {
+    let fixture = Fixture::new("hello");
+    fixture.write("target/package/hello-0.1.0-x86_64-fake-linux-gnu.tar.gz/in-the-way", "");
+
+    let run = fixture.loki(&["package"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("failed to write"), "{}", run.stderr());
+    assert_eq!(
+        archive(&fixture),
+        fixture.path("target/package/hello-0.1.0-x86_64-fake-linux-gnu.tar.gz")
+    );
+}
@@ -0,0 +1,108 @@
+mod support;
+
+use support::Fixture;
+use support::Run;
+
+/// The probes `run` ran, each a compile of standard input with `-Werror` and the flag probed.
+fn probes(run: &Run) -> Vec<&Vec<String>> {
+    run.invocations
+        .iter()
+        .filter(|f| f.first().is_some_and(|f| f == "-Werror") && f.last().is_some_and(|f| f == "-"))
+        .collect()
+}
+
+/// How often `run` ran the compiler with `argument`.
+fn runs_with(run: &Run, argument: &str) -> usize {
+    run.invocations
+        .iter()
+        .filter(|f| f.iter().any(|f| f == argument))
+        .count()
+}
+
+#[test]
+fn requested_flag_is_probed_once_and_then_cached() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build", "--color", "always"]);
+    run.assert_success();
+    assert_eq!(probes(&run).len(), 1);
+    assert_eq!(probes(&run)[0][1], "-fcolor-diagnostics");
+    assert!(run.stderr().contains("-fcolor-diagnostics: supported"));
+    assert_eq!(runs_with(&run, "-fcolor-diagnostics"), 3);
+
+    let run = fixture.loki(&["build", "--color", "always", "-vv"]);
+    run.assert_success();
+    assert!(probes(&run).is_empty());
+    assert!(run.stderr().contains("-fcolor-diagnostics: supported (cached)"));
+}
+
+#[test]
+fn changed_compiler_is_probed_again() {
+    let mut fixture = Fixture::new("hello");
+    fixture.loki(&["build", "--color", "always"]).assert_success();
+
+    fixture.env.push(("FAKE_CLANG_VERSION".to_owned(), "2.0.0".to_owned()));
+    let run = fixture.loki(&["build", "--color", "always"]);
+    run.assert_success();
+    assert_eq!(probes(&run).len(), 1);
+}
+
+#[test]
+fn no_probes_assumes_every_flag_is_unsupported() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build", "--color", "always", "--no-probes", "-vv"]);
+    run.assert_success();
+    assert!(probes(&run).is_empty());
+    assert_eq!(runs_with(&run, "-fcolor-diagnostics"), 0);
+    assert!(run
+        .stderr()
+        .contains("-fcolor-diagnostics: assumed unsupported (probes disabled)"));
+}
+
+#[test]
+fn flags_nobody_requests_are_never_probed() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build", "--color", "never"]);
+    run.assert_success();
+    assert_eq!(probes(&run).len(), 1);
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(probes(&run).is_empty());
+}
+
+#[test]
+fn host_triple_is_asked_for_again_only_for_another_compiler() {
+    let mut fixture = Fixture::new("hello");
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(runs_with(&run, "-dumpmachine"), 1);
+    assert!(fixture
+        .read("target/debug/artifacts.json")
+        .contains("x86_64-fake-linux-gnu"));
+
+    let source = fixture.read("src/main.c");
+    fixture.write("src/main.c", &format!("{}\n/* changed */\n", source));
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(runs_with(&run, "-dumpmachine"), 0);
+
+    fixture.env.push(("FAKE_CLANG_VERSION".to_owned(), "2.0.0".to_owned()));
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert_eq!(runs_with(&run, "-dumpmachine"), 1);
+}
+
+#[test]
+fn build_without_changes_fingerprints_the_compiler_once() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[config-header]\nname = \"config.h\"\n", f));
+    fixture.loki(&["build", "--color", "always"]).assert_success();
+
+    let run = fixture.loki(&["build", "--color", "always"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+    assert_eq!(run.invocations, [["--version"]]);
+}
@@ -0,0 +1,142 @@
+mod support;
+
+use support::Fixture;
+use support::Run;
+
+/// The arguments of every compile of `run`.
+fn compiles(run: &Run) -> Vec<&Vec<String>> {
+    run.invocations.iter().filter(|f| f.iter().any(|f| f == "-c")).collect()
+}
+
+fn has(arguments: &[String], flag: &str) -> bool {
+    arguments.iter().any(|f| f == flag)
+}
+
+#[test]
+fn build_uses_the_debug_profile() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    for compile in compiles(&run) {
+        assert!(has(compile, "-O0") && has(compile, "-g"));
+        assert!(!has(compile, "-O2"));
+    }
+    assert!(fixture.path("target/debug/hello").is_file());
+}
+
+#[test]
+fn release_uses_the_configured_optimization_without_debug_info() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["build", "--release"]);
+    run.assert_success();
+    for compile in compiles(&run) {
+        assert!(has(compile, "-O2") && !has(compile, "-g"));
+    }
+    assert!(fixture.path("target/release/hello").is_file());
+    assert!(!fixture.path("target/debug").exists());
+}
+
+#[test]
+fn profiles_keep_their_own_artifacts() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build", "--debug"]).assert_success();
+    fixture.loki(&["build", "--release"]).assert_success();
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run.compiled().is_empty());
+    assert!(!run.linked());
+    assert!(fixture.path("target/release/hello").is_file());
+
+    for profile in ["debug", "release"] {
+        let artifacts: serde_json::Value =
+            serde_json::from_str(&fixture.read(&format!("target/{}/artifacts.json", profile))).unwrap();
+        assert_eq!(artifacts["profile"], profile);
+    }
+}
+
+#[test]
+fn profile_setting_selects_the_profile_unless_debug_is_given() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[build]\nprofile = \"release\"\n", f));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(compiles(&run).iter().all(|f| has(f, "-O2")));
+    assert!(fixture.path("target/release/hello").is_file());
+    assert!(!fixture.path("target/debug").exists());
+
+    let run = fixture.loki(&["build", "--debug"]);
+    run.assert_success();
+    assert!(compiles(&run).iter().all(|f| has(f, "-O0")));
+    assert!(fixture.path("target/debug/hello").is_file());
+
+    let run = fixture.loki(&["env"]);
+    assert!(String::from_utf8_lossy(&run.output.stdout).contains("profile  = release (loki.toml)"));
+}
+
+#[test]
+fn profile_tables_override_the_defaults() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| {
+        format!(
+            "{}\n[profile.debug]\ndebug = false\n\n[profile.release]\nopt-level = 3\ncflags = [\"-DNDEBUG\"]\nldflags \
+             = [\"-s\"]\n",
+            f
+        )
+    });
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    for compile in compiles(&run) {
+        assert!(has(compile, "-O0") && !has(compile, "-g") && !has(compile, "-DNDEBUG"));
+    }
+
+    let run = fixture.loki(&["build", "--release"]);
+    run.assert_success();
+    for compile in compiles(&run) {
+        assert!(has(compile, "-O3") && has(compile, "-DNDEBUG"));
+    }
+    assert!(has(link(&run), "-O3") && has(link(&run), "-s"));
+}
+
+/// The arguments of the link of `run`.
+fn link(run: &Run) -> &Vec<String> {
+    run.invocations
+        .iter()
+        .find(|f| f.iter().any(|f| f.ends_with(".o")) && !has(f, "-c"))
+        .unwrap()
+}
+
+#[test]
+fn link_opt_level_applies_to_the_link_alone() {
+    let fixture = Fixture::new("hello");
+    let run = fixture.loki(&["build", "--release"]);
+    run.assert_success();
+    assert!(has(link(&run), "-O2"));
+
+    fixture.edit_manifest(|f| f.replace("opt-level = 2", "opt-level = 2\nlink-opt-level = 3"));
+    fixture.loki(&["clean"]).assert_success();
+
+    let run = fixture.loki(&["build", "--release"]);
+    run.assert_success();
+    let compiles = compiles(&run);
+    assert_eq!(compiles.len(), 2);
+    for compile in compiles {
+        assert!(has(compile, "-O2") && !has(compile, "-O3"));
+    }
+    let link = link(&run);
+    assert!(has(link, "-O3") && !has(link, "-O2"));
+}
+
+#[test]
+fn unknown_profile_keys_are_rejected() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| format!("{}\n[profile.release]\noptimise = true\n", f));
+
+    let run = fixture.loki(&["build", "--release"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("unknown field `optimise`"));
+}
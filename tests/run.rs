@@ -0,0 +1,36 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn runs_the_binary_from_the_project_root_with_the_arguments_after_the_separator() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["run", "-j", "1", "--", "--port", "8080"]);
+    run.assert_success();
+    let project = fixture.path("").canonicalize().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&run.output.stdout),
+        format!("ran in {} with --port 8080\n", project.display())
+    );
+}
+
+#[test]
+fn exits_with_the_program_exit_code() {
+    let mut fixture = Fixture::new("hello");
+    fixture.env.push(("FAKE_PROGRAM_STATUS".to_owned(), "3".to_owned()));
+
+    let run = fixture.loki(&["run"]);
+    assert_eq!(run.output.status.code(), Some(3));
+}
+
+#[test]
+fn failed_build_does_not_launch_the_binary() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+    fixture.write("src/broken.c", "FAKE_ERROR\n");
+
+    let run = fixture.loki(&["run"]);
+    assert!(run.stderr().contains("fake error"));
+    assert!(!String::from_utf8_lossy(&run.output.stdout).contains("ran in"));
+}
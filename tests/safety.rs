@@ -0,0 +1,31 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn project_in_the_home_directory_is_refused() {
+    let mut fixture = Fixture::new("hello");
+    fixture
+        .env
+        .push(("HOME".to_owned(), fixture.path("").display().to_string()));
+
+    let run = fixture.loki(&["build"]);
+    assert!(!run.success());
+    assert!(
+        run.stderr().contains("because it is your home directory"),
+        "{}",
+        run.stderr()
+    );
+    assert!(run.invocations.is_empty());
+}
+
+#[test]
+fn allow_root_project_builds_in_the_home_directory() {
+    let mut fixture = Fixture::new("hello");
+    fixture
+        .env
+        .push(("HOME".to_owned(), fixture.path("").display().to_string()));
+
+    fixture.loki(&["build", "--allow-root-project"]).assert_success();
+    assert!(fixture.path("target/debug/hello").is_file());
+}
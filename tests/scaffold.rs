@@ -0,0 +1,79 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn add_file_creates_a_source_and_with_header_a_header() {
+    let fixture = Fixture::new("hello");
+
+    fixture.loki(&["add-file", "parser"]).assert_success();
+    assert_eq!(fixture.read("src/parser.c"), "/* parser.c */\n");
+    assert!(!fixture.path("include/parser.h").exists());
+
+    fixture.loki(&["add-file", "net/socket", "--header"]).assert_success();
+    assert_eq!(fixture.read("src/net/socket.c"), "#include \"net/socket.h\"\n");
+    assert_eq!(
+        fixture.read("include/net/socket.h"),
+        "#ifndef NET_SOCKET_H\n#define NET_SOCKET_H\n\n#endif /* NET_SOCKET_H */\n"
+    );
+}
+
+#[test]
+fn add_file_overwrites_existing_files_only_with_force() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["add-file", "greeting", "--header"]);
+    assert!(!run.success());
+    assert!(
+        run.stderr().contains("already exists; pass --force to overwrite it"),
+        "{}",
+        run.stderr()
+    );
+    assert!(fixture.read("src/greeting.c").contains("greet"));
+
+    fixture
+        .loki(&["add-file", "greeting", "--header", "--force"])
+        .assert_success();
+    assert_eq!(fixture.read("src/greeting.c"), "#include \"greeting.h\"\n");
+}
+
+#[test]
+fn add_file_rejects_names_outside_the_source_directory() {
+    let fixture = Fixture::new("hello");
+
+    for name in ["../escape", "/tmp/escape", ""] {
+        let run = fixture.loki(&["add-file", name]);
+        assert!(!run.success(), "{}", name);
+        assert!(run.stderr().contains("is not a valid file name"), "{}", run.stderr());
+    }
+    assert!(!fixture.path("escape.c").exists());
+}
+
+#[test]
+fn remove_file_deletes_the_files_once_confirmed() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki_with_input(&["remove-file", "greeting"], "n\n");
+    run.assert_success();
+    assert!(run.stderr().contains("[y/N]"), "{}", run.stderr());
+    assert!(fixture.path("src/greeting.c").exists() && fixture.path("include/greeting.h").exists());
+
+    fixture
+        .loki_with_input(&["remove-file", "greeting"], "y\n")
+        .assert_success();
+    assert!(!fixture.path("src/greeting.c").exists() && !fixture.path("include/greeting.h").exists());
+}
+
+#[test]
+fn remove_file_with_yes_does_not_ask() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki_with_input(&["remove-file", "greeting", "--yes"], "");
+    run.assert_success();
+    assert!(!run.stderr().contains("[y/N]"), "{}", run.stderr());
+    assert!(!fixture.path("src/greeting.c").exists() && !fixture.path("include/greeting.h").exists());
+
+    let run = fixture.loki(&["remove-file", "greeting", "--yes"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("no source or header named 'greeting' exists"));
+}
@@ -0,0 +1,136 @@
+mod support;
+
+use support::Fixture;
+
+/// The sorted names of the sources `arguments` compiled.
+fn compiled(fixture: &Fixture, arguments: &[&str]) -> Vec<String> {
+    let run = fixture.loki(arguments);
+    run.assert_success();
+    let mut compiled = run.compiled();
+    compiled.sort();
+    compiled
+}
+
+#[test]
+fn source_dir_at_the_root_finds_sources_anywhere_but_target_and_tests() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("[package]\n", "[package]\nsource-dir = \".\"\n"));
+    fixture.write("extra/util.c", "void util(void) {}\n");
+    fixture.write("tests/check.c", "FAKE_ERROR\n");
+    fixture.write("target/stray.c", "FAKE_ERROR\n");
+
+    assert_eq!(compiled(&fixture, &["build"]), ["greeting.c", "main.c", "util.c"]);
+}
+
+#[test]
+fn source_dirs_are_all_searched() {
+    let fixture = Fixture::new("hello");
+    fixture.write("app/main.c", &fixture.read("src/main.c"));
+    fixture.write("lib/greeting.c", &fixture.read("src/greeting.c"));
+    fixture.write("src/ignored.c", "FAKE_ERROR\n");
+    fixture.edit_manifest(|f| f.replace("[package]\n", "[package]\nsource-dirs = [\"lib\", \"app\"]\n"));
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let sources = run
+        .invocations
+        .iter()
+        .filter(|f| f.iter().any(|f| f == "-c"))
+        .filter_map(|f| f.iter().find(|f| f.ends_with(".c")))
+        .collect::<Vec<_>>();
+    assert_eq!(sources.len(), 2, "{:?}", sources);
+    assert!(sources.iter().any(|f| f.ends_with("app/main.c")));
+    assert!(sources.iter().any(|f| f.ends_with("lib/greeting.c")));
+}
+
+#[test]
+fn overlapping_source_dirs_are_rejected() {
+    let fixture = Fixture::new("hello");
+    fixture.write("src/net/socket.c", "void open_socket(void) {}\n");
+    fixture.edit_manifest(|f| f.replace("[package]\n", "[package]\nsource-dirs = [\"src\", \"src/net\"]\n"));
+
+    let run = fixture.loki(&["build"]);
+    assert!(!run.success());
+    assert!(
+        run.stderr().contains("source directories 'src' and 'src/net' overlap"),
+        "{}",
+        run.stderr()
+    );
+    assert!(run.compiled().is_empty());
+}
+
+#[test]
+fn missing_or_conflicting_source_dirs_are_rejected() {
+    for (lines, error) in [
+        ("source-dir = \"lib\"", "source directory 'lib' does not exist"),
+        ("source-dirs = []", "'source-dirs' is empty"),
+        (
+            "source-dir = \"src\"\nsource-dirs = [\"src\"]",
+            "only one of 'source-dir' and 'source-dirs' may be set",
+        ),
+    ] {
+        let fixture = Fixture::new("hello");
+        fixture.edit_manifest(|f| f.replace("[package]\n", &format!("[package]\n{}\n", lines)));
+
+        let run = fixture.loki(&["build"]);
+        assert!(!run.success(), "{}", lines);
+        assert!(run.stderr().contains(error), "{}", run.stderr());
+    }
+}
+
+#[test]
+fn listed_sources_are_the_only_ones_built() {
+    let fixture = Fixture::new("hello");
+    fixture.write("vendor/greeting.c", &fixture.read("src/greeting.c"));
+    fixture.write("src/greeting.c", "FAKE_ERROR\n");
+    fixture.edit_manifest(|f| {
+        f.replace(
+            "[package]\n",
+            "[package]\nsources = [\"src/main.c\", \"vendor/greeting.c\"]\n",
+        )
+    });
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    assert!(run
+        .invocations
+        .iter()
+        .any(|f| f.iter().any(|f| f.ends_with("vendor/greeting.c"))));
+    assert_eq!(run.compiled().len(), 2);
+}
+
+#[test]
+fn listed_sources_must_exist_and_be_c_or_cpp() {
+    for (lines, error) in [
+        (
+            "sources = [\"src/main.c\", \"src/gone.c\"]",
+            "listed source 'src/gone.c' does not exist",
+        ),
+        (
+            "sources = [\"src/main.c\", \"include/greeting.h\"]",
+            "listed source 'include/greeting.h' is not a C or C++ source",
+        ),
+    ] {
+        let fixture = Fixture::new("hello");
+        fixture.edit_manifest(|f| f.replace("[package]\n", &format!("[package]\n{}\n", lines)));
+
+        let run = fixture.loki(&["build"]);
+        assert!(!run.success(), "{}", lines);
+        assert!(run.stderr().contains(error), "{}", run.stderr());
+        assert!(run.compiled().is_empty());
+    }
+}
+
+#[test]
+fn discover_sources_adds_the_source_directories_to_the_listed_sources() {
+    let fixture = Fixture::new("hello");
+    fixture.write("vendor/extra.c", "void extra(void) {}\n");
+    fixture.edit_manifest(|f| {
+        f.replace(
+            "[package]\n",
+            "[package]\nsources = [\"vendor/extra.c\", \"src/main.c\"]\ndiscover-sources = true\n",
+        )
+    });
+
+    assert_eq!(compiled(&fixture, &["build"]), ["extra.c", "greeting.c", "main.c"]);
+}
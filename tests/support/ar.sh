@@ -0,0 +1,18 @@
+#!/bin/sh
+# Stands in for ar in loki's integration tests, writing the archive `ar rcs <archive> <objects>` names and logging
+# like the fake clang. Like ar, it records when the archive was made unless the operation has the `D` modifier.
+
+for argument in "$(basename "$0")" "$@"; do
+    printf '%s\n' "$argument" >> "$LOKI_TEST_LOG"
+done
+printf '\n' >> "$LOKI_TEST_LOG"
+
+operation="$1"
+shift
+archive="$1"
+shift
+printf 'archive of: %s\n' "$*" > "$archive"
+case "$operation" in
+    *D*) ;;
+    *) date +%s%N >> "$archive" ;;
+esac
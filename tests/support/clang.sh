@@ -0,0 +1,107 @@
+#!/bin/sh
+# Stands in for clang and clang++ in loki's integration tests. Every invocation is appended to $LOKI_TEST_LOG, the
+# program's name and then one argument per line followed by a blank line, and whatever `-o` names is written with
+# placeholder contents, so builds are fast and don't depend on a real compiler. It answers `--version` with
+# $FAKE_CLANG_VERSION and `-dumpmachine` with a made-up triple. Like clang, `-gsplit-dwarf` also writes the output with
+# its extension replaced by `.dwo`, and `-MF` writes a dependency file listing the quoted includes found next to the
+# source or in `-iquote` directories. Linked binaries are scripts that print their working directory and arguments and
+# exit with $FAKE_PROGRAM_STATUS, or with 1 after printing "fake test failure" if any object was compiled from a source
+# containing FAKE_TEST_FAILURE. Sources containing FAKE_ERROR fail to compile, and each line of a source containing
+# FAKE_WARNING makes a warning. With $FAKE_CLANG_RUNNING naming a directory, each compile also lingers for a moment and
+# counts how many compiles are running alongside it into $FAKE_CLANG_RUNNING.counts.
+
+basename "$0" >> "$LOKI_TEST_LOG"
+for argument in "$@"; do
+    printf '%s\n' "$argument" >> "$LOKI_TEST_LOG"
+done
+printf '\n' >> "$LOKI_TEST_LOG"
+
+output=""
+depfile=""
+source=""
+quote_directories=""
+split_dwarf=""
+compiling=""
+previous=""
+for argument in "$@"; do
+    case "$previous" in
+        -o) output="$argument" ;;
+        -MF) depfile="$argument" ;;
+        -iquote) quote_directories="$quote_directories $argument" ;;
+    esac
+    case "$argument" in
+        --version)
+            echo "fake clang version ${FAKE_CLANG_VERSION:-1.0.0}"
+            exit 0
+            ;;
+        -dumpmachine)
+            echo "x86_64-fake-linux-gnu"
+            exit 0
+            ;;
+        -gsplit-dwarf)
+            split_dwarf=1
+            ;;
+        -c | -S)
+            compiling=1
+            ;;
+        *.c | *.cpp | *.cc | *.cxx)
+            source="$argument"
+            if grep -q FAKE_ERROR "$argument" 2> /dev/null; then
+                echo "$argument:1:1: error: fake error" >&2
+                exit 1
+            fi
+            grep -n FAKE_WARNING "$argument" 2> /dev/null | while IFS=: read -r line _; do
+                echo "$argument:$line:1: warning: fake warning" >&2
+            done
+            ;;
+    esac
+    previous="$argument"
+done
+
+if [ -n "$FAKE_CLANG_RUNNING" ] && [ -n "$source" ] && [ -n "$output" ]; then
+    touch "$FAKE_CLANG_RUNNING/$$"
+    sleep 0.3
+    ls "$FAKE_CLANG_RUNNING" | wc -l >> "$FAKE_CLANG_RUNNING.counts"
+    rm "$FAKE_CLANG_RUNNING/$$"
+fi
+
+if [ -n "$output" ] && [ -z "$compiling" ]; then
+    cat > "$output" << 'PROGRAM'
+#!/bin/sh
+printf 'ran in %s with' "$(pwd)"
+printf ' %s' "$@"
+echo
+PROGRAM
+    for argument in "$@"; do
+        case "$argument" in
+            *.o)
+                if grep -q FAKE_TEST_FAILURE "$argument" 2> /dev/null; then
+                    printf 'echo "fake test failure" >&2\nexit 1\n' >> "$output"
+                fi
+                ;;
+        esac
+    done
+    echo 'exit "${FAKE_PROGRAM_STATUS:-0}"' >> "$output"
+    chmod +x "$output"
+elif [ -n "$output" ]; then
+    printf 'output of: %s\n' "$*" > "$output"
+    if grep -q FAKE_TEST_FAILURE "$source" 2> /dev/null; then
+        echo FAKE_TEST_FAILURE >> "$output"
+    fi
+    if [ -n "$split_dwarf" ]; then
+        printf 'debug info of: %s\n' "$*" > "${output%.*}.dwo"
+    fi
+fi
+
+if [ -n "$depfile" ]; then
+    dependencies="$source"
+    for header in $(sed -n 's/^#include "\(.*\)"/\1/p' "$source"); do
+        for directory in "$(dirname "$source")" $quote_directories; do
+            if [ -f "$directory/$header" ]; then
+                dependencies="$dependencies $directory/$header"
+                break
+            fi
+        done
+    done
+    printf '%s: %s\n' "$output" "$dependencies" > "$depfile"
+fi
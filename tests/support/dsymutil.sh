@@ -0,0 +1,15 @@
+#!/bin/sh
+# Stands in for dsymutil in loki's integration tests, creating the bundle named by `-o` and logging like the fake clang.
+
+for argument in dsymutil "$@"; do
+    printf '%s\n' "$argument" >> "$LOKI_TEST_LOG"
+done
+printf '\n' >> "$LOKI_TEST_LOG"
+
+previous=""
+for argument in "$@"; do
+    if [ "$previous" = "-o" ]; then
+        mkdir -p "$argument/Contents/Resources/DWARF"
+    fi
+    previous="$argument"
+done
@@ -0,0 +1,264 @@
+//! Helpers for running the loki binary against copies of the projects in `tests/fixtures`, with a fake compiler
+//! that records how it was invoked.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+const FAKE_CLANG: &str = include_str!("clang.sh");
+const FAKE_DSYMUTIL: &str = include_str!("dsymutil.sh");
+const FAKE_AR: &str = include_str!("ar.sh");
+const FAKE_PKG_CONFIG: &str = include_str!("pkg-config.sh");
+
+/// A fixture project copied into a temporary directory, along with a home directory and the fake compiler, so
+/// nothing outside the directory affects or is affected by a test.
+pub struct Fixture {
+    root:    TempDir,
+    /// Environment variables set for every run, such as `FAKE_CLANG_VERSION`.
+    pub env: Vec<(String, String)>,
+}
+
+/// What a single run of loki did.
+pub struct Run {
+    pub output:      Output,
+    /// The arguments of each invocation of a fake tool, in order.
+    pub invocations: Vec<Vec<String>>,
+    /// The program of each invocation, such as `clang` or `ar`, in the same order.
+    pub programs:    Vec<String>,
+}
+
+impl Fixture {
+    /// Copies `tests/fixtures/<name>` into a new temporary directory.
+    pub fn new(name: &str) -> Self {
+        let root = tempfile::tempdir().unwrap();
+        let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        let project = root.path().join("project");
+
+        for entry in WalkDir::new(&source) {
+            let entry = entry.unwrap();
+            let destination = project.join(entry.path().strip_prefix(&source).unwrap());
+            match entry.file_type().is_dir() {
+                true => fs::create_dir_all(&destination).unwrap(),
+                false => _ = fs::copy(entry.path(), &destination).unwrap(),
+            }
+        }
+
+        let bin = root.path().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        for (name, script) in [
+            ("clang", FAKE_CLANG),
+            ("clang++", FAKE_CLANG),
+            ("dsymutil", FAKE_DSYMUTIL),
+            ("ar", FAKE_AR),
+            ("pkg-config", FAKE_PKG_CONFIG),
+        ] {
+            fs::write(bin.join(name), script).unwrap();
+            fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        fs::create_dir_all(root.path().join("home")).unwrap();
+
+        Self {
+            root,
+            env: Vec::new(),
+        }
+    }
+
+    /// Puts a copy of the fake `tool`, such as `clang` or `ar`, on `PATH` as `name`, like a cross toolchain's
+    /// `<triple>-gcc`. It logs invocations under `name`.
+    pub fn install(&self, name: &str, tool: &str) {
+        let bin = self.root.path().join("bin");
+        fs::copy(bin.join(tool), bin.join(name)).unwrap();
+    }
+
+    /// Creates a directory outside the project for a test's own bookkeeping.
+    pub fn scratch(&self, name: &str) -> PathBuf {
+        let path = self.root.path().join(name);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// The path of `relative` inside the copied project.
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.root.path().join("project").join(relative)
+    }
+
+    pub fn read(&self, relative: &str) -> String {
+        fs::read_to_string(self.path(relative)).unwrap()
+    }
+
+    pub fn write(&self, relative: &str, contents: &str) {
+        let path = self.path(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Rewrites the project's `loki.toml` as `edit` returns it, given its current contents.
+    pub fn edit_manifest(&self, edit: impl FnOnce(&str) -> String) {
+        let manifest = self.read("loki.toml");
+        self.write("loki.toml", &edit(&manifest));
+    }
+
+    /// Names of the files in `target/debug/obj`.
+    pub fn objects(&self) -> Vec<String> {
+        let mut objects = fs::read_dir(self.path("target/debug/obj"))
+            .unwrap()
+            .map(|f| f.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        objects.sort();
+        objects
+    }
+
+    /// Runs loki with `arguments` in the project directory.
+    pub fn loki(&self, arguments: &[&str]) -> Run {
+        self.loki_in("", arguments)
+    }
+
+    /// Runs loki with `arguments` in `relative`, a directory inside the project.
+    pub fn loki_in(&self, relative: &str, arguments: &[&str]) -> Run {
+        _ = fs::remove_file(self.log());
+        let output = self
+            .command(arguments)
+            .current_dir(self.path(relative))
+            .output()
+            .unwrap();
+        let (programs, invocations) = self.logged();
+
+        Run {
+            output,
+            invocations,
+            programs,
+        }
+    }
+
+    /// Runs loki with `arguments` in the project directory, writing `input` to its standard input.
+    pub fn loki_with_input(&self, arguments: &[&str], input: &str) -> Run {
+        _ = fs::remove_file(self.log());
+        let mut child = self
+            .command(arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        let (programs, invocations) = self.logged();
+
+        Run {
+            output,
+            invocations,
+            programs,
+        }
+    }
+
+    /// Starts loki with `arguments` in the project directory without waiting for it, for commands that keep running.
+    /// Its standard error is piped.
+    pub fn spawn(&self, arguments: &[&str]) -> Child {
+        _ = fs::remove_file(self.log());
+        self.command(arguments).stderr(Stdio::piped()).spawn().unwrap()
+    }
+
+    /// The programs and arguments of each invocation of a fake tool since loki was last started, as in `Run`.
+    pub fn logged(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        fs::read_to_string(self.log())
+            .unwrap_or_default()
+            .split_terminator("\n\n")
+            .map(|f| {
+                let mut lines = f.lines().map(str::to_owned);
+                (lines.next().unwrap_or_default(), lines.collect())
+            })
+            .unzip()
+    }
+
+    fn log(&self) -> PathBuf {
+        self.root.path().join("invocations.log")
+    }
+
+    fn command(&self, arguments: &[&str]) -> Command {
+        let home = self.root.path().join("home");
+        let path = std::env::join_paths(
+            [self.root.path().join("bin")]
+                .into_iter()
+                .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default())),
+        )
+        .unwrap();
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_loki"));
+        command
+            .args(arguments)
+            .current_dir(self.path(""))
+            .env("PATH", path)
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("XDG_CACHE_HOME", home.join(".cache"))
+            .env("LOKI_TEST_LOG", self.log())
+            .env("CI", "1")
+            .env_remove("MAKEFLAGS")
+            .envs(self.env.iter().map(|(key, value)| (key, value)));
+        command
+    }
+
+    /// Waits up to ten seconds for `condition` to hold, checking it every so often.
+    pub fn wait_until(&self, condition: impl Fn(&Self) -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if condition(self) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        condition(self)
+    }
+}
+
+impl Run {
+    pub fn success(&self) -> bool {
+        self.output.status.success()
+    }
+
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.output.stderr).into_owned()
+    }
+
+    /// Panics with loki's output unless the run succeeded.
+    pub fn assert_success(&self) -> &Self {
+        assert!(self.success(), "loki failed:\n{}", self.stderr());
+        self
+    }
+
+    /// File names of the sources that were compiled, in order. Probes and feature checks, which read standard input,
+    /// are left out.
+    pub fn compiled(&self) -> Vec<String> {
+        self.invocations
+            .iter()
+            .filter(|f| f.iter().any(|f| f == "-c" || f == "-S"))
+            .filter_map(|f| {
+                f.iter()
+                    .find(|f| [".c", ".cpp", ".cc", ".cxx"].iter().any(|g| f.ends_with(g)))
+            })
+            .map(|f| Path::new(f).file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Whether the compiler was invoked to link.
+    pub fn linked(&self) -> bool {
+        self.invocations
+            .iter()
+            .any(|f| f.iter().any(|f| f.ends_with(".o")) && !f.iter().any(|f| f == "-c"))
+    }
+}
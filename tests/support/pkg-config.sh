@@ -0,0 +1,30 @@
+#!/bin/sh
+# Stands in for pkg-config in loki's integration tests, logging like the fake clang. Only the packages `fake` and
+# `other` exist, with made-up include and library directories.
+
+for argument in pkg-config "$@"; do
+    printf '%s\n' "$argument" >> "$LOKI_TEST_LOG"
+done
+printf '\n' >> "$LOKI_TEST_LOG"
+
+mode="$1"
+shift
+cflags=""
+libs=""
+for package in "$@"; do
+    case "$package" in
+        fake|other)
+            cflags="$cflags -I/opt/$package/include"
+            libs="$libs -L/opt/$package/lib -l$package"
+            ;;
+        *)
+            echo "Package $package was not found in the pkg-config search path." >&2
+            exit 1
+            ;;
+    esac
+done
+
+case "$mode" in
+    --cflags) echo "$cflags" ;;
+    --libs) echo "$libs" ;;
+esac
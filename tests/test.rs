@@ -0,0 +1,99 @@
+mod support;
+
+use support::Fixture;
+
+/// Writes a test that passes unless `fails`.
+fn with_test(fixture: &Fixture, name: &str, fails: bool) {
+    fixture.write(
+        &format!("tests/{}.c", name),
+        &format!(
+            "#include \"greeting.h\"\n{}int main(void) {{ return 0; }}\n",
+            if fails { "FAKE_TEST_FAILURE\n" } else { "" }
+        ),
+    );
+}
+
+#[test]
+fn links_each_test_with_the_project_objects_except_main() {
+    let fixture = Fixture::new("hello");
+    with_test(&fixture, "greeting_test", false);
+    with_test(&fixture, "other_test", false);
+
+    let run = fixture.loki(&["test"]);
+    run.assert_success();
+
+    let mut compiled = run.compiled();
+    compiled.sort();
+    assert_eq!(compiled, ["greeting.c", "greeting_test.c", "other_test.c"]);
+
+    let links = run
+        .invocations
+        .iter()
+        .filter(|f| f.iter().any(|f| f.ends_with(".o")) && !f.iter().any(|f| f == "-c"))
+        .collect::<Vec<_>>();
+    assert_eq!(links.len(), 2);
+    for link in links {
+        assert_eq!(link.iter().filter(|f| f.ends_with(".o")).count(), 2);
+    }
+    assert!(fixture.path("target/debug/tests/greeting_test").is_file());
+    assert!(fixture.path("target/debug/tests/other_test").is_file());
+    assert!(run.stderr().contains("2 passed, 0 failed"));
+    assert!(!String::from_utf8_lossy(&run.output.stdout).contains("ran in"));
+    assert!(!run.stderr().contains("ran in"));
+}
+
+#[test]
+fn failing_test_fails_and_shows_its_output() {
+    let fixture = Fixture::new("hello");
+    with_test(&fixture, "good_test", false);
+    with_test(&fixture, "bad_test", true);
+
+    let run = fixture.loki(&["test"]);
+    assert!(!run.success());
+
+    let stderr = run.stderr();
+    assert!(stderr.contains("bad_test (exited with status 1)"));
+    assert!(stderr.contains("fake test failure"));
+    assert!(stderr.contains("1 passed, 1 failed"));
+    // Only the failing test's output is shown.
+    assert_eq!(stderr.matches("ran in").count(), 1);
+}
+
+#[test]
+fn runs_only_the_named_test() {
+    let fixture = Fixture::new("hello");
+    with_test(&fixture, "good_test", false);
+    with_test(&fixture, "bad_test", true);
+
+    let run = fixture.loki(&["test", "good_test"]);
+    run.assert_success();
+    assert!(!run.compiled().contains(&"bad_test.c".to_owned()));
+    assert!(run.stderr().contains("1 passed, 0 failed"));
+
+    let run = fixture.loki(&["test", "missing_test"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("no test named 'missing_test'"));
+}
+
+#[test]
+fn no_tests_is_not_a_failure() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["test"]);
+    run.assert_success();
+    assert!(run.stderr().contains("no tests found"));
+    assert!(run.compiled().is_empty());
+}
+
+#[test]
+fn tests_are_not_part_of_a_flat_project() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("[package]", "[package]\nsource-dir = \".\""));
+    with_test(&fixture, "greeting_test", false);
+
+    let run = fixture.loki(&["build"]);
+    run.assert_success();
+    let mut compiled = run.compiled();
+    compiled.sort();
+    assert_eq!(compiled, ["greeting.c", "main.c"]);
+}
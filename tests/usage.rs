@@ -0,0 +1,27 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn usage_errors_exit_with_a_failure() {
+    let fixture = Fixture::new("hello");
+
+    for (arguments, error) in [
+        (
+            &["run", "--emit=asm"][..],
+            "'run' needs a binary and can't emit assembly",
+        ),
+        (&["test", "--emit=asm"], "'test' needs binaries and can't emit assembly"),
+        (&["why"], "'why' requires a source or object"),
+        (&["includes"], "'includes' requires a source file"),
+        (&["add-file"], "'add-file' requires a file name"),
+        (&["remove-file"], "'remove-file' requires a file name"),
+        (&["new"], "'new' requires a project name"),
+        (&["frobnicate"], "unknown command/flag 'frobnicate'"),
+    ] {
+        let run = fixture.loki(arguments);
+        assert!(!run.success(), "{:?}", arguments);
+        assert!(run.stderr().contains(error), "{}", run.stderr());
+        assert!(run.invocations.is_empty(), "{:?}", arguments);
+    }
+}
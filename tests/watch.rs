@@ -0,0 +1,129 @@
+mod support;
+
+use std::process::Child;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::thread;
+use std::time::Duration;
+
+use support::Fixture;
+
+/// How many times a source whose path ends with `name` was compiled since loki was started.
+fn compiles_of(fixture: &Fixture, name: &str) -> usize {
+    fixture
+        .logged()
+        .1
+        .iter()
+        .filter(|f| f.iter().any(|f| f == "-c") && f.iter().any(|f| f.ends_with(name)))
+        .count()
+}
+
+/// How many times the binary was linked since loki was started.
+fn links(fixture: &Fixture) -> usize {
+    fixture
+        .logged()
+        .1
+        .iter()
+        .filter(|f| f.iter().any(|f| f.ends_with(".o")) && !f.iter().any(|f| f == "-c"))
+        .count()
+}
+
+/// Interrupts the watch as Ctrl-C would, returning how it exited and what it printed.
+fn interrupt(watch: Child) -> (ExitStatus, String) {
+    Command::new("kill")
+        .arg("-INT")
+        .arg(watch.id().to_string())
+        .status()
+        .unwrap();
+    let output = watch.wait_with_output().unwrap();
+    (output.status, String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn rebuilds_when_a_source_changes_until_interrupted() {
+    let fixture = Fixture::new("hello");
+
+    let watch = fixture.spawn(&["watch"]);
+    assert!(fixture.wait_until(|f| f.path("target/debug/hello").is_file()));
+
+    let source = fixture.read("src/greeting.c");
+    fixture.write("src/greeting.c", &format!("{}\n/* changed */\n", source));
+    assert!(fixture.wait_until(|f| links(f) == 2));
+    // Gives the build a moment to finish after linking, so it is followed by a status line.
+    thread::sleep(Duration::from_millis(500));
+
+    let (status, stderr) = interrupt(watch);
+    assert!(status.success(), "{}", stderr);
+    assert_eq!(compiles_of(&fixture, "greeting.c"), 2);
+    assert_eq!(compiles_of(&fixture, "main.c"), 1);
+    assert!(stderr.contains("Changed"));
+    assert_eq!(stderr.matches("build succeeded, watching for changes").count(), 2);
+}
+
+#[test]
+fn reloads_the_manifest_and_survives_a_broken_one() {
+    let fixture = Fixture::new("hello");
+    let manifest = fixture.read("loki.toml");
+
+    let watch = fixture.spawn(&["watch"]);
+    assert!(fixture.wait_until(|f| f.path("target/debug/hello").is_file()));
+
+    fixture.write("loki.toml", "[package\n");
+    thread::sleep(Duration::from_secs(1));
+    fixture.write(
+        "loki.toml",
+        &format!("{}\n[build]\ncflags = [\"-DWATCHED\"]\n", manifest),
+    );
+    assert!(fixture.wait_until(|f| f.logged().1.iter().any(|f| f.iter().any(|f| f == "-DWATCHED"))));
+
+    let (status, stderr) = interrupt(watch);
+    assert!(status.success(), "{}", stderr);
+    assert!(stderr.contains("build failed, watching for changes"));
+}
+
+#[test]
+fn writes_under_target_or_matching_watch_ignore_trigger_no_build() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| {
+        f.replace(
+            "[package]\n",
+            "[package]\nsource-dir = \".\"\nwatch-ignore = [\"src/generated\"]\n",
+        )
+    });
+
+    let watch = fixture.spawn(&["watch"]);
+    assert!(fixture.wait_until(|f| f.path("target/debug/hello").is_file()));
+
+    fixture.write("target/debug/stray.c", "int stray;\n");
+    fixture.write("src/generated/table.c", "int table;\n");
+    thread::sleep(Duration::from_secs(1));
+    assert_eq!(links(&fixture), 1);
+
+    let source = fixture.read("src/greeting.c");
+    fixture.write("src/greeting.c", &format!("{}\n/* changed */\n", source));
+    assert!(fixture.wait_until(|f| links(f) == 2));
+    thread::sleep(Duration::from_millis(500));
+
+    let (status, stderr) = interrupt(watch);
+    assert!(status.success(), "{}", stderr);
+    assert_eq!(stderr.matches("Changed").count(), 1, "{}", stderr);
+    assert!(stderr.contains("greeting.c"), "{}", stderr);
+}
+
+#[test]
+fn invalid_watch_ignore_pattern_fails_the_build_without_ending_the_watch() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("[package]\n", "[package]\nwatch-ignore = [\"src/[\"]\n"));
+
+    let watch = fixture.spawn(&["watch"]);
+    thread::sleep(Duration::from_secs(1));
+    let (status, stderr) = interrupt(watch);
+    assert!(status.success(), "{}", stderr);
+    assert!(
+        stderr.contains("invalid pattern 'src/[' in 'watch-ignore'"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("build failed, watching for changes"), "{}", stderr);
+    assert!(!fixture.path("target/debug/hello").exists());
+}
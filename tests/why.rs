@@ -0,0 +1,57 @@
+mod support;
+
+use support::Fixture;
+
+#[test]
+fn explains_a_discovered_source() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["why", "src/greeting.c"]);
+    run.assert_success();
+    let stdout = String::from_utf8_lossy(&run.output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "src/greeting.c");
+    assert_eq!(lines[1], "  included because it was found in source directory src");
+    assert!(lines[2].starts_with("  compiled to target/debug/obj/"));
+    assert!(lines[3].starts_with("    clang -std=c17"));
+    assert!(lines[3].ends_with("src/greeting.c"));
+    assert_eq!(lines[4], "  needed by hello (bin) target/debug/hello");
+    assert!(run.compiled().is_empty());
+}
+
+#[test]
+fn explains_an_object_by_its_source() {
+    let fixture = Fixture::new("hello");
+    fixture.loki(&["build"]).assert_success();
+
+    let run = fixture.loki(&["why", "src/main.c"]);
+    let stdout = String::from_utf8_lossy(&run.output.stdout);
+    let object = stdout
+        .lines()
+        .find_map(|f| f.strip_prefix("  compiled to "))
+        .and_then(|f| f.strip_suffix(" with"))
+        .unwrap();
+
+    let run = fixture.loki(&["why", object]);
+    run.assert_success();
+    assert!(String::from_utf8_lossy(&run.output.stdout).starts_with("src/main.c\n"));
+}
+
+#[test]
+fn explains_a_listed_source() {
+    let fixture = Fixture::new("hello");
+    fixture.edit_manifest(|f| f.replace("[package]", "[package]\nsources = [\"src/main.c\", \"src/greeting.c\"]"));
+
+    let run = fixture.loki(&["why", "src/main.c"]);
+    run.assert_success();
+    assert!(String::from_utf8_lossy(&run.output.stdout).contains("included because it was listed in 'package.sources'"));
+}
+
+#[test]
+fn rejects_files_outside_the_build() {
+    let fixture = Fixture::new("hello");
+
+    let run = fixture.loki(&["why", "include/greeting.h"]);
+    assert!(!run.success());
+    assert!(run.stderr().contains("is neither a source nor an object of hello"));
+}